@@ -0,0 +1,2 @@
+-- Drop indexes
+DROP INDEX if EXISTS idx_blocks_timestamp_bigint;
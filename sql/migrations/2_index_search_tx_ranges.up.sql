@@ -0,0 +1,3 @@
+-- Index the block timestamps, which are stored as text, for searches within
+-- a timestamp range
+CREATE INDEX IF NOT EXISTS idx_blocks_timestamp_bigint ON blocks ((timestamp::BIGINT));
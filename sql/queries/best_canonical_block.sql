@@ -0,0 +1,12 @@
+SELECT
+  height,
+  state_hash,
+  TIMESTAMP
+FROM
+  blocks
+WHERE
+  chain_status='canonical'
+ORDER BY
+  height DESC
+LIMIT
+  1
@@ -0,0 +1,15 @@
+/* Each canonical block yields a `block_added` event whose sequence number is
+its height. Canonical blocks are never orphaned, so the feed is append-only and
+its sequence numbers stay stable as the archive grows */
+SELECT
+  height AS "sequence!",
+  state_hash AS "state_hash!"
+FROM
+  blocks
+WHERE
+  chain_status='canonical'
+  AND height>=$1
+ORDER BY
+  height ASC
+LIMIT
+  $2
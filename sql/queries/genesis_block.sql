@@ -0,0 +1,15 @@
+SELECT
+  height,
+  state_hash
+FROM
+  blocks
+WHERE
+  (
+    $1::TEXT IS NULL
+    AND parent_id IS NULL
+  )
+  OR state_hash=$1
+ORDER BY
+  height ASC
+LIMIT
+  1
@@ -71,42 +71,131 @@ WITH
       AND ica.id=cri.internal_command_id
       AND ica.sequence_no=cri.sequence_no
       AND ica.secondary_sequence_no=cri.secondary_sequence_no
+      LEFT JOIN LATERAL (
+        /* Whether the command has operations of the type searched for */
+        SELECT
+          TRUE AS matched
+        WHERE
+          ica.command_type::TEXT=ANY ($16::TEXT[])
+          AND (
+            NOT $17::BOOLEAN
+            OR EXISTS (
+              SELECT
+                1
+              FROM
+                accounts_created AS ac
+                INNER JOIN account_identifiers AS ai ON ac.account_identifier_id=ai.id
+              WHERE
+                ac.block_id=ica.block_id
+                AND ai.public_key_id=ica.receiver_id
+                AND ica.sequence_no=least(
+                  (
+                    SELECT
+                      min(bic2.sequence_no)
+                    FROM
+                      blocks_internal_commands AS bic2
+                      INNER JOIN internal_commands AS ic2 ON bic2.internal_command_id=ic2.id
+                    WHERE
+                      ica.receiver_id=ic2.receiver_id
+                      AND bic2.block_id=ica.block_id
+                      AND bic2.status='applied'
+                  ),
+                  (
+                    SELECT
+                      min(buc2.sequence_no)
+                    FROM
+                      blocks_user_commands AS buc2
+                      INNER JOIN user_commands AS uc2 ON buc2.user_command_id=uc2.id
+                    WHERE
+                      ica.receiver_id=uc2.receiver_id
+                      AND buc2.block_id=ica.block_id
+                      AND buc2.status='applied'
+                  )
+                )
+            )
+          )
+      ) AS type_match ON TRUE
     WHERE
       (
         $1>=b.height
         OR $1 IS NULL
       )
       AND (
-        $2=ica.hash
-        OR $2 IS NULL
+        $19::BIGINT<=b.height
+        OR $19 IS NULL
       )
       AND (
-        (
-          (
-            $3=ica.receiver
-            OR $3=cri.coinbase_receiver
-          )
-          OR $3 IS NULL
-        )
+        $20::BIGINT<=b.timestamp::BIGINT
+        OR $20 IS NULL
       )
       AND (
-        $4=''
-        OR $4 IS NULL
-      )
-      AND (
-        $5=ica.status
-        OR $5 IS NULL
-      )
-      AND (
-        $6=ica.status
-        OR $6 IS NULL
+        $21::BIGINT>=b.timestamp::BIGINT
+        OR $21 IS NULL
       )
       AND (
+        /* The given conditions, combined with and */
         (
-          $7=ica.receiver
-          OR $7=cri.coinbase_receiver
+          NOT $15::BOOLEAN
+          AND (
+            $2=ica.hash
+            OR $2 IS NULL
+          )
+          AND (
+            (
+              (
+                $3=ica.receiver
+                OR $3=cri.coinbase_receiver
+              )
+              OR $3 IS NULL
+            )
+          )
+          AND (
+            $4=''
+            OR $4 IS NULL
+          )
+          AND (
+            $5=ica.status
+            OR $5 IS NULL
+          )
+          AND (
+            $6=ica.status
+            OR $6 IS NULL
+          )
+          AND (
+            (
+              $7=ica.receiver
+              OR $7=cri.coinbase_receiver
+            )
+            OR $7 IS NULL
+          )
+          AND (
+            type_match.matched
+            OR $16 IS NULL
+          )
+          AND (
+            $18::BOOLEAN
+            OR $18 IS NULL
+          )
+        )
+        /* Or combined with or, so that any of them matches */
+        OR (
+          $15
+          AND (
+            $2=ica.hash
+            OR (
+              $3=ica.receiver
+              OR $3=cri.coinbase_receiver
+            )
+            AND coalesce($4='', TRUE)
+            OR $5=ica.status
+            OR $6=ica.status
+            OR $7=ica.receiver
+            OR $7=cri.coinbase_receiver
+            OR type_match.matched
+            OR $18
+            OR num_nonnulls($2, $3, $5, $6, $7, $16, $18)=0
+          )
         )
-        OR $7 IS NULL
       )
   ),
   id_count AS (
@@ -114,6 +203,9 @@ WITH
       count(*) AS total_count
     FROM
       internal_commands_info
+    WHERE
+      /* Unless the count is already known */
+      $23::BOOLEAN
   )
 SELECT
   i.*,
@@ -126,7 +218,47 @@ FROM
       *
     FROM
       internal_commands_info
+    WHERE
+      /* Commands after the cursor of a cursor-paged search, in the order of
+      the search */
+      $10::BIGINT IS NULL
+      OR NOT $22::BOOLEAN
+      AND (height, block_id, sequence_no, secondary_sequence_no, id)>($10, $11, $12, $13, $14)
+      OR $22
+      AND (height, block_id, sequence_no, secondary_sequence_no, id)<($10, $11, $12, $13, $14)
     ORDER BY
+      /* By height, block and sequence numbers in ascending or descending order
+      when sorted, by block and id otherwise */
+      CASE
+        WHEN NOT $22 THEN height
+      END,
+      CASE
+        WHEN NOT $22 THEN block_id
+      END,
+      CASE
+        WHEN NOT $22 THEN sequence_no
+      END,
+      CASE
+        WHEN NOT $22 THEN secondary_sequence_no
+      END,
+      CASE
+        WHEN NOT $22 THEN id
+      END,
+      CASE
+        WHEN $22 THEN height
+      END DESC,
+      CASE
+        WHEN $22 THEN block_id
+      END DESC,
+      CASE
+        WHEN $22 THEN sequence_no
+      END DESC,
+      CASE
+        WHEN $22 THEN secondary_sequence_no
+      END DESC,
+      CASE
+        WHEN $22 THEN id
+      END DESC,
       block_id,
       id,
       sequence_no,
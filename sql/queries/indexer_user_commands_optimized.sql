@@ -54,34 +54,120 @@ WITH
         )
       )
       INNER JOIN blocks AS b ON uca.block_id=b.id
+      LEFT JOIN LATERAL (
+        /* Whether the command has operations of the type searched for */
+        SELECT
+          TRUE AS matched
+        WHERE
+          uca.command_type::TEXT=ANY ($15::TEXT[])
+          AND (
+            NOT $16::BOOLEAN
+            OR uca.status='applied'
+            AND EXISTS (
+              SELECT
+                1
+              FROM
+                accounts_created AS ac
+                INNER JOIN account_identifiers AS ai ON ac.account_identifier_id=ai.id
+              WHERE
+                ac.block_id=uca.block_id
+                AND ai.public_key_id=uca.receiver_id
+                AND uca.sequence_no=least(
+                  (
+                    SELECT
+                      min(bic2.sequence_no)
+                    FROM
+                      blocks_internal_commands AS bic2
+                      INNER JOIN internal_commands AS ic2 ON bic2.internal_command_id=ic2.id
+                    WHERE
+                      uca.receiver_id=ic2.receiver_id
+                      AND bic2.block_id=uca.block_id
+                      AND bic2.status='applied'
+                  ),
+                  (
+                    SELECT
+                      min(buc2.sequence_no)
+                    FROM
+                      blocks_user_commands AS buc2
+                      INNER JOIN user_commands AS uc2 ON buc2.user_command_id=uc2.id
+                    WHERE
+                      uca.receiver_id=uc2.receiver_id
+                      AND buc2.block_id=uca.block_id
+                      AND buc2.status='applied'
+                  )
+                )
+            )
+          )
+      ) AS type_match ON TRUE
     WHERE
       (
         $1>=b.height
         OR $1 IS NULL
       )
       AND (
-        $2=uca.hash
-        OR $2 IS NULL
-      )
-      AND (
-        $3=pk.value
-        OR $3 IS NULL
-      )
-      AND (
-        $4=''
-        OR $4 IS NULL
+        $18::BIGINT<=b.height
+        OR $18 IS NULL
       )
       AND (
-        $5=uca.status
-        OR $5 IS NULL
+        $19::BIGINT<=b.timestamp::BIGINT
+        OR $19 IS NULL
       )
       AND (
-        $6=uca.status
-        OR $6 IS NULL
+        $20::BIGINT>=b.timestamp::BIGINT
+        OR $20 IS NULL
       )
       AND (
-        $7=pk.value
-        OR $7 IS NULL
+        /* The given conditions, combined with and */
+        (
+          NOT $14::BOOLEAN
+          AND (
+            $2=uca.hash
+            OR $2 IS NULL
+          )
+          AND (
+            $3=pk.value
+            OR $3 IS NULL
+          )
+          AND (
+            $4=''
+            OR $4 IS NULL
+          )
+          AND (
+            $5=uca.status
+            OR $5 IS NULL
+          )
+          AND (
+            $6=uca.status
+            OR $6 IS NULL
+          )
+          AND (
+            $7=pk.value
+            OR $7 IS NULL
+          )
+          AND (
+            type_match.matched
+            OR $15 IS NULL
+          )
+          AND (
+            $17::BOOLEAN
+            OR $17 IS NULL
+          )
+        )
+        /* Or combined with or, so that any of them matches */
+        OR (
+          $14
+          AND (
+            $2=uca.hash
+            OR $3=pk.value
+            AND coalesce($4='', TRUE)
+            OR $5=uca.status
+            OR $6=uca.status
+            OR $7=pk.value
+            OR type_match.matched
+            OR $17
+            OR num_nonnulls($2, $3, $5, $6, $7, $15, $17)=0
+          )
+        )
       )
   ),
   id_count AS (
@@ -89,6 +175,9 @@ WITH
       count(*) AS total_count
     FROM
       user_command_info
+    WHERE
+      /* Unless the count is already known */
+      $22::BOOLEAN
   )
 SELECT
   u.*,
@@ -104,7 +193,41 @@ FROM
       *
     FROM
       user_command_info
+    WHERE
+      /* Commands after the cursor of a cursor-paged search, in the order of
+      the search */
+      $10::BIGINT IS NULL
+      OR NOT $21::BOOLEAN
+      AND (height, block_id, sequence_no, id)>($10, $11, $12, $13)
+      OR $21
+      AND (height, block_id, sequence_no, id)<($10, $11, $12, $13)
     ORDER BY
+      /* By height, block and sequence numbers in ascending or descending order
+      when sorted, by block and id otherwise */
+      CASE
+        WHEN NOT $21 THEN height
+      END,
+      CASE
+        WHEN NOT $21 THEN block_id
+      END,
+      CASE
+        WHEN NOT $21 THEN sequence_no
+      END,
+      CASE
+        WHEN NOT $21 THEN id
+      END,
+      CASE
+        WHEN $21 THEN height
+      END DESC,
+      CASE
+        WHEN $21 THEN block_id
+      END DESC,
+      CASE
+        WHEN $21 THEN sequence_no
+      END DESC,
+      CASE
+        WHEN $21 THEN id
+      END DESC,
       block_id,
       id,
       sequence_no
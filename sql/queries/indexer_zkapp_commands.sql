@@ -65,57 +65,103 @@ WITH
         OR $1 IS NULL
       )
       AND (
-        $2=zc.hash
-        OR $2 IS NULL
+        $17::BIGINT<=b.height
+        OR $17 IS NULL
       )
       AND (
+        $18::BIGINT<=b.timestamp::BIGINT
+        OR $18 IS NULL
+      )
+      AND (
+        $19::BIGINT>=b.timestamp::BIGINT
+        OR $19 IS NULL
+      )
+      AND (
+        /* The given conditions, combined with and */
         (
-          (
+          NOT $14::BOOLEAN
+          AND (
+            $2=zc.hash
+            OR $2 IS NULL
+          )
+          AND (
             (
-              $4=token_update_body.value
-              AND (
-                $3=pk_update_body.value
-                OR $3=pk_fee_payer.value
+              (
+                (
+                  $4=token_update_body.value
+                  AND (
+                    $3=pk_update_body.value
+                    OR $3=pk_fee_payer.value
+                  )
+                )
+              )
+              AND $3 IS NOT NULL
+              AND $4 IS NOT NULL
+            )
+            OR (
+              (
+                $3=pk_fee_payer.value
+                OR $3=pk_update_body.value
               )
+              AND $3 IS NOT NULL
+              AND $4 IS NULL
+            )
+            OR (
+              $3 IS NULL
+              AND $4 IS NULL
             )
           )
-          AND $3 IS NOT NULL
-          AND $4 IS NOT NULL
-        )
-        OR (
-          (
-            $3=pk_fee_payer.value
-            OR $3=pk_update_body.value
+          AND (
+            $5=bzc.status
+            OR $5 IS NULL
+          )
+          AND (
+            $6=bzc.status
+            OR $6 IS NULL
+          )
+          AND (
+            (
+              $7=pk_fee_payer.value
+              OR $7=pk_update_body.value
+            )
+            OR $7 IS NULL
+          )
+          AND (
+            $15::BOOLEAN
+            OR $15 IS NULL
+          )
+          AND (
+            $16::TEXT=token_update_body.value
+            OR $16 IS NULL
           )
-          AND $3 IS NOT NULL
-          AND $4 IS NULL
         )
+        /* Or combined with or, so that any of them matches */
         OR (
-          $3 IS NULL
-          AND $4 IS NULL
-        )
-      )
-      AND (
-        $5=bzc.status
-        OR $5 IS NULL
-      )
-      AND (
-        $6=bzc.status
-        OR $6 IS NULL
-      )
-      AND (
-        (
-          $7=pk_fee_payer.value
-          OR $7=pk_update_body.value
+          $14
+          AND (
+            $2=zc.hash
+            OR (
+              $3=pk_update_body.value
+              OR $3=pk_fee_payer.value
+            )
+            AND coalesce($4=token_update_body.value, TRUE)
+            OR $5=bzc.status
+            OR $6=bzc.status
+            OR $7=pk_fee_payer.value
+            OR $7=pk_update_body.value
+            OR $15
+            OR $16=token_update_body.value
+            OR num_nonnulls($2, $3, $5, $6, $7, $15, $16)=0
+          )
         )
-        OR $7 IS NULL
       )
   ),
   zkapp_commands_ids AS (
     SELECT DISTINCT
       id,
       block_id,
-      sequence_no
+      sequence_no,
+      height
     FROM
       zkapp_commands_info
   ),
@@ -124,6 +170,9 @@ WITH
       count(*) AS total_count
     FROM
       zkapp_commands_ids
+    WHERE
+      /* Unless the count is already known */
+      $21::BOOLEAN
   )
 SELECT
   zc.*,
@@ -135,7 +184,41 @@ FROM
       *
     FROM
       zkapp_commands_ids
+    WHERE
+      /* Commands after the cursor of a cursor-paged search, in the order of
+      the search */
+      $10::BIGINT IS NULL
+      OR NOT $20::BOOLEAN
+      AND (height, block_id, sequence_no, id)>($10, $11, $12, $13)
+      OR $20
+      AND (height, block_id, sequence_no, id)<($10, $11, $12, $13)
     ORDER BY
+      /* By height, block and sequence numbers in ascending or descending order
+      when sorted, by block and id otherwise */
+      CASE
+        WHEN NOT $20 THEN height
+      END,
+      CASE
+        WHEN NOT $20 THEN block_id
+      END,
+      CASE
+        WHEN NOT $20 THEN sequence_no
+      END,
+      CASE
+        WHEN NOT $20 THEN id
+      END,
+      CASE
+        WHEN $20 THEN height
+      END DESC,
+      CASE
+        WHEN $20 THEN block_id
+      END DESC,
+      CASE
+        WHEN $20 THEN sequence_no
+      END DESC,
+      CASE
+        WHEN $20 THEN id
+      END DESC,
       block_id,
       id,
       sequence_no
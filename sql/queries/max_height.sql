@@ -0,0 +1,4 @@
+SELECT
+  max(height) AS max_height
+FROM
+  blocks
@@ -0,0 +1,35 @@
+SELECT
+  b.state_hash AS "state_hash!",
+  b.height AS "height!",
+  b.chain_status AS "chain_status!: ChainStatus",
+  (
+    SELECT
+      max(height)
+    FROM
+      blocks
+  ) AS "best_height!"
+FROM
+  blocks AS b
+  INNER JOIN blocks_user_commands AS buc ON b.id=buc.block_id
+  INNER JOIN user_commands AS u ON u.id=buc.user_command_id
+WHERE
+  u.hash=$1
+UNION
+SELECT
+  b.state_hash AS "state_hash!",
+  b.height AS "height!",
+  b.chain_status AS "chain_status!: ChainStatus",
+  (
+    SELECT
+      max(height)
+    FROM
+      blocks
+  ) AS "best_height!"
+FROM
+  blocks AS b
+  INNER JOIN blocks_zkapp_commands AS bzc ON b.id=bzc.block_id
+  INNER JOIN zkapp_commands AS zc ON zc.id=bzc.zkapp_command_id
+WHERE
+  zc.hash=$1
+ORDER BY
+  "height!" DESC
@@ -1,5 +1,6 @@
 mod account_balance;
 mod block;
+mod block_transaction;
 mod cache;
 mod call;
 mod construction_combine;
@@ -10,6 +11,8 @@ mod construction_parse;
 mod construction_payloads;
 mod construction_preprocess;
 mod construction_submit;
+mod events_blocks;
+mod health;
 mod mempool;
 mod mempool_transaction;
 mod network_list;
@@ -8,7 +8,7 @@ use crate::{
   graphql::{
     Account3, AccountNonce, AnnotatedBalance, Balance, Length, QueryBalance, QueryBalanceVariables, StateHash, TokenId,
   },
-  util::Wrapper,
+  util::{begin_archive_transaction, with_statement_timeout, Wrapper},
   MinaMesh, MinaMeshError,
 };
 
@@ -18,7 +18,13 @@ impl MinaMesh {
     self.validate_network(&req.network_identifier).await?;
     let AccountIdentifier { address, metadata, .. } = *req.account_identifier;
     match req.block_identifier {
-      Some(block_identifier) => self.block_balance(address, metadata, *block_identifier).await,
+      Some(block_identifier) => {
+        let block_balance = self.block_balance(address, metadata, *block_identifier);
+        with_statement_timeout(self.statement_timeouts.account_balance, block_balance).await
+      }
+      None if self.archive_only => Err(MinaMeshError::EndpointUnavailable(
+        "/account/balance requires a block_identifier in archive-only mode".to_string(),
+      )),
       None => self.frontier_balance(address).await,
     }
   }
@@ -31,8 +37,9 @@ impl MinaMesh {
   ) -> Result<AccountBalanceResponse, MinaMeshError> {
     let index = partial_block_id.index;
     let hash = partial_block_id.hash;
+    let mut transaction = begin_archive_transaction(&self.pg_pool).await?;
     let block = sqlx::query_file!("sql/queries/maybe_block.sql", index, hash)
-      .fetch_optional(&self.pg_pool)
+      .fetch_optional(&mut *transaction)
       .await?
       .ok_or(MinaMeshError::BlockMissing(index, hash.clone()))?;
     let maybe_account_balance_info = sqlx::query_file!(
@@ -41,7 +48,7 @@ impl MinaMesh {
       block.height.ok_or(MinaMeshError::ChainInfoMissing)?,
       Wrapper(metadata).token_id_or_default()?
     )
-    .fetch_optional(&self.pg_pool)
+    .fetch_optional(&mut *transaction)
     .await?;
     match maybe_account_balance_info {
       None => Ok(AccountBalanceResponse {
@@ -65,7 +72,7 @@ impl MinaMesh {
         let nonce = account_balance_info.nonce;
         let last_relevant_command_balance = account_balance_info.balance.parse::<u64>()?;
         let timing_info = sqlx::query_file!("sql/queries/timing_info.sql", account_balance_info.timing_id)
-          .fetch_optional(&self.pg_pool)
+          .fetch_optional(&mut *transaction)
           .await?;
         let liquid_balance = match timing_info {
           Some(timing_info) => {
@@ -4,27 +4,50 @@ use coinbase_mesh::models::{
 };
 use serde::Serialize;
 use serde_json::json;
-use sqlx::FromRow;
+use sqlx::{FromRow, PgConnection};
 
 use crate::{
   generate_internal_command_transaction_identifier, generate_operations_internal_command,
   generate_operations_user_command, generate_operations_zkapp_command, generate_transaction_metadata,
-  util::DEFAULT_TOKEN_ID, ChainStatus, InternalCommandMetadata, InternalCommandType, MinaMesh, MinaMeshError,
-  TransactionStatus, UserCommandMetadata, UserCommandType, ZkAppCommand,
+  util::{begin_archive_transaction, with_statement_timeout, DEFAULT_TOKEN_ID},
+  ChainStatus, InternalCommandMetadata, InternalCommandType, MinaMesh, MinaMeshError, TransactionStatus,
+  UserCommandMetadata, UserCommandType, ZkAppCommand,
 };
 
 /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/app/rosetta/lib/block.ml#L7
 impl MinaMesh {
   pub async fn block(&self, request: BlockRequest) -> Result<BlockResponse, MinaMeshError> {
     self.validate_network(&request.network_identifier).await?;
-    let partial_block_identifier = *request.block_identifier;
-    let metadata = match self.block_metadata(&partial_block_identifier).await? {
+    with_statement_timeout(self.statement_timeouts.block, self.archive_block(*request.block_identifier)).await
+  }
+
+  async fn archive_block(
+    &self,
+    partial_block_identifier: PartialBlockIdentifier,
+  ) -> Result<BlockResponse, MinaMeshError> {
+    if let Some(hash) = &partial_block_identifier.hash {
+      if let Some(response) = self.cached_block(hash, partial_block_identifier.index) {
+        return Ok(response);
+      }
+    }
+    // The queries of the request share a transaction, in which the statement
+    // timeout of the endpoint applies
+    let mut transaction = begin_archive_transaction(&self.pg_pool).await?;
+    let metadata = match self.block_metadata(&mut transaction, &partial_block_identifier).await? {
       Some(metadata) => metadata,
       None => return Err(MinaMeshError::BlockMissing(partial_block_identifier.index, partial_block_identifier.hash)),
     };
+    let canonical = metadata.chain_status == Some(ChainStatus::Canonical);
+    if canonical && partial_block_identifier.hash.is_none() {
+      if let Some(response) = self.cached_block(&metadata.state_hash, None) {
+        return Ok(response);
+      }
+    }
     let parent_block_metadata = match &metadata.parent_id {
       Some(parent_id) => {
-        sqlx::query_file_as!(BlockMetadata, "sql/queries/query_id.sql", parent_id).fetch_optional(&self.pg_pool).await?
+        sqlx::query_file_as!(BlockMetadata, "sql/queries/query_id.sql", parent_id)
+          .fetch_optional(&mut *transaction)
+          .await?
       }
       None => None,
     };
@@ -33,31 +56,56 @@ impl MinaMesh {
       Some(block_metadata) => BlockIdentifier::new(block_metadata.height, block_metadata.state_hash),
       None => block_identifier.clone(),
     };
-    let (user_commands, internal_commands, zkapp_commands) = tokio::try_join!(
-      self.user_commands(&metadata),
-      self.internal_commands(&metadata),
-      self.zkapp_commands(&metadata)
-    )?;
+    let user_commands = self.user_commands(&mut transaction, &metadata).await?;
+    let internal_commands = self.internal_commands(&mut transaction, &metadata).await?;
+    let zkapp_commands = self.zkapp_commands(&mut transaction, &metadata).await?;
 
     let all_commands: Vec<_> =
       internal_commands.into_iter().chain(user_commands.into_iter()).chain(zkapp_commands.into_iter()).collect();
+    let (transactions, other_transactions) = match self.max_block_transactions {
+      Some(max_block_transactions) if all_commands.len() > max_block_transactions => {
+        tracing::debug!(
+          "Block {} has {} transactions, returning identifiers only",
+          block_identifier.hash,
+          all_commands.len()
+        );
+        (vec![], Some(all_commands.into_iter().map(|transaction| *transaction.transaction_identifier).collect()))
+      }
+      _ => (all_commands, None),
+    };
 
-    Ok(BlockResponse {
+    let response = BlockResponse {
       block: Some(Box::new(Block {
         block_identifier: Box::new(block_identifier),
         parent_block_identifier: Box::new(parent_block_identifier),
         timestamp: metadata.timestamp.parse()?,
-        transactions: all_commands,
+        transactions,
         metadata: Some(json!({ "creator": metadata.creator })),
       })),
-      other_transactions: None,
+      other_transactions,
+    };
+    // Canonical blocks never change, unlike pending ones which may be orphaned
+    if canonical {
+      self.cache.blocks.insert(metadata.state_hash, response.clone());
+    }
+    Ok(response)
+  }
+
+  /// A cached response for the block, as long as it has the requested index.
+  fn cached_block(&self, hash: &str, index: Option<i64>) -> Option<BlockResponse> {
+    self.cache.blocks.get(&hash.to_string()).filter(|response| {
+      index.is_none_or(|index| response.block.as_ref().is_some_and(|block| block.block_identifier.index == index))
     })
   }
 
   // TODO: use default token value, check how to best handle this
-  pub async fn user_commands(&self, metadata: &BlockMetadata) -> Result<Vec<Transaction>, MinaMeshError> {
+  pub async fn user_commands(
+    &self,
+    connection: &mut PgConnection,
+    metadata: &BlockMetadata,
+  ) -> Result<Vec<Transaction>, MinaMeshError> {
     let metadata = sqlx::query_file_as!(UserCommandMetadata, "sql/queries/user_commands.sql", metadata.id)
-      .fetch_all(&self.pg_pool)
+      .fetch_all(&mut *connection)
       .await?;
     let transactions = metadata
       .into_iter()
@@ -76,10 +124,14 @@ impl MinaMesh {
     Ok(transactions)
   }
 
-  pub async fn internal_commands(&self, metadata: &BlockMetadata) -> Result<Vec<Transaction>, MinaMeshError> {
+  pub async fn internal_commands(
+    &self,
+    connection: &mut PgConnection,
+    metadata: &BlockMetadata,
+  ) -> Result<Vec<Transaction>, MinaMeshError> {
     let metadata =
       sqlx::query_file_as!(InternalCommandMetadata, "sql/queries/internal_commands.sql", metadata.id, DEFAULT_TOKEN_ID)
-        .fetch_all(&self.pg_pool)
+        .fetch_all(&mut *connection)
         .await?;
 
     let transactions = metadata
@@ -100,10 +152,14 @@ impl MinaMesh {
     Ok(transactions)
   }
 
-  pub async fn zkapp_commands(&self, metadata: &BlockMetadata) -> Result<Vec<Transaction>, MinaMeshError> {
+  pub async fn zkapp_commands(
+    &self,
+    connection: &mut PgConnection,
+    metadata: &BlockMetadata,
+  ) -> Result<Vec<Transaction>, MinaMeshError> {
     let zkapp_commands =
       sqlx::query_file_as!(ZkAppCommand, "sql/queries/zkapp_commands.sql", metadata.id, DEFAULT_TOKEN_ID)
-        .fetch_all(&self.pg_pool)
+        .fetch_all(&mut *connection)
         .await?;
     let transactions = zkapp_commands_to_transactions(zkapp_commands);
     Ok(transactions)
@@ -111,26 +167,30 @@ impl MinaMesh {
 
   pub async fn block_metadata(
     &self,
+    connection: &mut PgConnection,
     PartialBlockIdentifier { index, hash }: &PartialBlockIdentifier,
   ) -> Result<Option<BlockMetadata>, sqlx::Error> {
-    if let (Some(index), Some(hash)) = (&index, &hash) {
+    let block_metadata = if let (Some(index), Some(hash)) = (&index, &hash) {
       sqlx::query_file_as!(BlockMetadata, "sql/queries/query_both.sql", hash.to_string(), index)
-        .fetch_optional(&self.pg_pool)
+        .fetch_optional(&mut *connection)
         .await
     } else if let Some(index) = index {
-      let record = sqlx::query_file!("sql/queries/max_canonical_height.sql").fetch_one(&self.pg_pool).await?;
+      let record = sqlx::query_file!("sql/queries/max_canonical_height.sql").fetch_one(&mut *connection).await?;
       if index <= &record.max_canonical_height.unwrap() {
         sqlx::query_file_as!(BlockMetadata, "sql/queries/query_canonical.sql", index)
-          .fetch_optional(&self.pg_pool)
+          .fetch_optional(&mut *connection)
           .await
       } else {
-        sqlx::query_file_as!(BlockMetadata, "sql/queries/query_pending.sql", index).fetch_optional(&self.pg_pool).await
+        sqlx::query_file_as!(BlockMetadata, "sql/queries/query_pending.sql", index)
+          .fetch_optional(&mut *connection)
+          .await
       }
     } else if let Some(hash) = &hash {
-      sqlx::query_file_as!(BlockMetadata, "sql/queries/query_hash.sql", hash).fetch_optional(&self.pg_pool).await
+      sqlx::query_file_as!(BlockMetadata, "sql/queries/query_hash.sql", hash).fetch_optional(&mut *connection).await
     } else {
-      sqlx::query_file_as!(BlockMetadata, "sql/queries/query_best.sql").fetch_optional(&self.pg_pool).await
-    }
+      sqlx::query_file_as!(BlockMetadata, "sql/queries/query_best.sql").fetch_optional(&mut *connection).await
+    };
+    block_metadata
   }
 }
 
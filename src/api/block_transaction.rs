@@ -0,0 +1,41 @@
+use anyhow::Result;
+use coinbase_mesh::models::{
+  BlockIdentifier, BlockTransactionRequest, BlockTransactionResponse, PartialBlockIdentifier,
+};
+
+use crate::{util::begin_archive_transaction, MinaMesh, MinaMeshError};
+
+/// https://docs.cdp.coinbase.com/mesh/reference/blocktransaction
+impl MinaMesh {
+  pub async fn block_transaction(
+    &self,
+    request: BlockTransactionRequest,
+  ) -> Result<BlockTransactionResponse, MinaMeshError> {
+    self.validate_network(&request.network_identifier).await?;
+    let BlockIdentifier { index, hash } = *request.block_identifier;
+    let mut archive_transaction = begin_archive_transaction(&self.pg_pool).await?;
+    let metadata = self
+      .block_metadata(
+        &mut archive_transaction,
+        &PartialBlockIdentifier { index: Some(index), hash: Some(hash.clone()) },
+      )
+      .await?
+      .ok_or(MinaMeshError::BlockMissing(Some(index), Some(hash)))?;
+
+    let transaction_hash = request.transaction_identifier.hash;
+    // Internal command identifiers have the form
+    // `<command_type>:<sequence_no>:<secondary_sequence_no>:<hash>`
+    let transactions = if transaction_hash.contains(':') {
+      self.internal_commands(&mut archive_transaction, &metadata).await?
+    } else {
+      let user_commands = self.user_commands(&mut archive_transaction, &metadata).await?;
+      user_commands.into_iter().chain(self.zkapp_commands(&mut archive_transaction, &metadata).await?).collect()
+    };
+
+    let transaction = transactions
+      .into_iter()
+      .find(|transaction| transaction.transaction_identifier.hash == transaction_hash)
+      .ok_or(MinaMeshError::TransactionNotFound(transaction_hash))?;
+    Ok(BlockTransactionResponse { transaction: Box::new(transaction) })
+  }
+}
@@ -1,12 +1,108 @@
-use anyhow::Result;
-use coinbase_mesh::models::{CallRequest, CallResponse};
+use coinbase_mesh::models::{BlockIdentifier, CallRequest, CallResponse, TransactionIdentifier};
+use cynic::QueryBuilder;
+use serde::Serialize;
 
-use crate::MinaMesh;
+use crate::{
+  graphql::{QueryTransactionStatus, QueryTransactionStatusVariables},
+  ChainStatus, MinaMesh, MinaMeshError,
+};
+
+/// Where a submitted transaction stands, from the mempool to the canonical
+/// chain.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum SubmittedTransactionStatus {
+  Pending,
+  Included,
+  Canonical,
+  Orphaned,
+  Unknown,
+}
+
+#[derive(Debug, Serialize)]
+struct TransactionStatusResult {
+  transaction_identifier: TransactionIdentifier,
+  status: SubmittedTransactionStatus,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  block_identifier: Option<BlockIdentifier>,
+  /// Number of blocks on top of the including block.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  depth: Option<i64>,
+}
 
 /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/app/rosetta/lib/construction.ml#L849
 impl MinaMesh {
-  pub async fn call(&self, request: CallRequest) -> Result<CallResponse> {
+  pub async fn call(&self, request: CallRequest) -> Result<CallResponse, MinaMeshError> {
     self.validate_network(&request.network_identifier).await?;
-    Ok(CallResponse::new(serde_json::Value::Null, true))
+    match request.method.as_str() {
+      "transaction_status" => {
+        let transaction_identifier: TransactionIdentifier = request
+          .parameters
+          .get("transaction_identifier")
+          .cloned()
+          .and_then(|value| serde_json::from_value(value).ok())
+          .ok_or_else(|| {
+            MinaMeshError::JsonParse(Some("transaction_status expects a transaction_identifier parameter".to_string()))
+          })?;
+        let result = self.transaction_status(transaction_identifier).await?;
+        let result = serde_json::to_value(result).map_err(|e| MinaMeshError::Exception(e.to_string()))?;
+        // The status of a transaction moves on as blocks are produced
+        Ok(CallResponse::new(result, false))
+      }
+      method => Err(MinaMeshError::JsonParse(Some(format!("Unsupported call method: {}", method)))),
+    }
+  }
+
+  /// Combines archive lookups by hash with the daemon's transaction pool. A
+  /// transaction in a canonical or pending block wins over the pool, which in
+  /// turn wins over orphaned blocks, as orphaned transactions get re-added to
+  /// the pool.
+  async fn transaction_status(
+    &self,
+    transaction_identifier: TransactionIdentifier,
+  ) -> Result<TransactionStatusResult, MinaMeshError> {
+    let blocks = sqlx::query_file!("sql/queries/transaction_status.sql", transaction_identifier.hash)
+      .fetch_all(&self.pg_pool)
+      .await?;
+    let find_block = |chain_status: ChainStatus| blocks.iter().find(|block| block.chain_status == chain_status);
+
+    let included = find_block(ChainStatus::Canonical)
+      .map(|block| (SubmittedTransactionStatus::Canonical, block))
+      .or_else(|| find_block(ChainStatus::Pending).map(|block| (SubmittedTransactionStatus::Included, block)));
+    if let Some((status, block)) = included {
+      return Ok(TransactionStatusResult {
+        transaction_identifier,
+        status,
+        block_identifier: Some(BlockIdentifier::new(block.height, block.state_hash.clone())),
+        depth: Some(block.best_height - block.height),
+      });
+    }
+
+    let QueryTransactionStatus { pooled_user_commands, pooled_zkapp_commands } = self
+      .graphql_client
+      .send(QueryTransactionStatus::build(QueryTransactionStatusVariables {
+        hashes: Some(vec![transaction_identifier.hash.as_str()]),
+      }))
+      .await?;
+    if !pooled_user_commands.is_empty() || !pooled_zkapp_commands.is_empty() {
+      return Ok(TransactionStatusResult {
+        transaction_identifier,
+        status: SubmittedTransactionStatus::Pending,
+        block_identifier: None,
+        depth: None,
+      });
+    }
+
+    let orphaned_block = find_block(ChainStatus::Orphaned);
+    Ok(TransactionStatusResult {
+      transaction_identifier,
+      status: if orphaned_block.is_some() {
+        SubmittedTransactionStatus::Orphaned
+      } else {
+        SubmittedTransactionStatus::Unknown
+      },
+      block_identifier: orphaned_block.map(|block| BlockIdentifier::new(block.height, block.state_hash.clone())),
+      depth: None,
+    })
   }
 }
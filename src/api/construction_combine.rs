@@ -1,7 +1,16 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
-use coinbase_mesh::models::{ConstructionCombineRequest, ConstructionCombineResponse, SignatureType};
+use coinbase_mesh::models::{
+  ConstructionCombineRequest, ConstructionCombineResponse, NetworkIdentifier, Signature, SignatureType,
+};
+use mina_signer::PubKey;
 
-use crate::{signer_utils::decode_signature, MinaMesh, MinaMeshError, TransactionSigned, TransactionUnsigned};
+use crate::{
+  generate_operations_user_command,
+  signer_utils::{decode_signature, signer_network_id, verify_signature, verify_zkapp_signature},
+  MinaMesh, MinaMeshError, TransactionSigned, TransactionUnsigned,
+};
 
 /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/app/rosetta/lib/construction.ml#L561
 impl MinaMesh {
@@ -12,6 +21,10 @@ impl MinaMesh {
     self.validate_network(&request.network_identifier).await?;
 
     let unsigned_transaction = TransactionUnsigned::from_json_string(&request.unsigned_transaction)?;
+    if unsigned_transaction.zkapp_command.is_some() {
+      return self.combine_zkapp_command(&request.network_identifier, &unsigned_transaction, &request.signatures);
+    }
+
     let signatures = request.signatures;
     if signatures.len() != 1 {
       return Err(MinaMeshError::SignatureInvalid(format!("Expected 1 signature, found {}", signatures.len())));
@@ -24,16 +37,116 @@ impl MinaMesh {
       )));
     }
 
+    let decoded_signature = decode_signature(signature.hex_bytes.as_str())?;
+
     hex::decode(&unsigned_transaction.random_oracle_input)
       .map_err(|e| MinaMeshError::JsonParse(Some(format!("Decoding of randomOracleInput failed: {}", e))))?;
-    // TODO: Verify the random oracle input
 
-    decode_signature(signature.hex_bytes.as_str())?;
+    self.check_transaction(&unsigned_transaction)?;
+    let user_command_payload = if let Some(payment) = &unsigned_transaction.payment {
+      let operations = generate_operations_user_command(payment);
+      self.validate_unsigned_transaction(
+        &unsigned_transaction,
+        &operations,
+        payment.valid_until,
+        payment.memo.clone(),
+      )?
+    } else if let Some(stake_delegation) = &unsigned_transaction.stake_delegation {
+      let operations = generate_operations_user_command(stake_delegation);
+      self.validate_unsigned_transaction(
+        &unsigned_transaction,
+        &operations,
+        stake_delegation.valid_until,
+        stake_delegation.memo.clone(),
+      )?
+    } else {
+      return Err(MinaMeshError::JsonParse(Some(
+        "Unsigned transaction must have one of: payment, stake_delegation, zkapp_command".to_string(),
+      )));
+    };
+
+    let fee_payer = user_command_payload.fee_payer.into_address();
+    let fee_payer_pk = PubKey::from_address(&fee_payer)
+      .map_err(|e| MinaMeshError::PublicKeyFormatNotValid(format!("Fee payer pk decompression failed: {}", e)))?;
+    let network_id = signer_network_id(&request.network_identifier);
+    if !verify_signature(&decoded_signature, &fee_payer_pk, &user_command_payload.to_random_oracle_input(), network_id)
+    {
+      return Err(MinaMeshError::SignatureInvalid(format!(
+        "Signature does not verify against fee payer {} on network {}:{}",
+        fee_payer, request.network_identifier.blockchain, request.network_identifier.network
+      )));
+    }
 
     let payment = unsigned_transaction.payment;
     let stake_delegation = unsigned_transaction.stake_delegation;
 
-    let signed_transaction = TransactionSigned { signature: signature.hex_bytes.clone(), payment, stake_delegation };
+    let signed_transaction =
+      TransactionSigned { signature: signature.hex_bytes.clone(), payment, stake_delegation, zkapp_command: None };
+    let signed_transaction_json = signed_transaction.as_json_string()?;
+
+    Ok(ConstructionCombineResponse::new(signed_transaction_json))
+  }
+
+  /// Expects one signature over the full commitment from each signer of the
+  /// zkApp command, identified by the account of its signing payload.
+  fn combine_zkapp_command(
+    &self,
+    network_identifier: &NetworkIdentifier,
+    unsigned_transaction: &TransactionUnsigned,
+    signatures: &[Signature],
+  ) -> Result<ConstructionCombineResponse, MinaMeshError> {
+    self.check_transaction(unsigned_transaction)?;
+    let zkapp_command = self.validate_unsigned_zkapp_command(unsigned_transaction)?;
+
+    let signers = zkapp_command.signers();
+    if signatures.len() != signers.len() {
+      return Err(MinaMeshError::SignatureInvalid(format!(
+        "Expected {} signatures, found {}",
+        signers.len(),
+        signatures.len()
+      )));
+    }
+
+    let (_, full_commitment) = zkapp_command.commitments()?;
+    let network_id = signer_network_id(network_identifier);
+    let mut signatures_by_signer = HashMap::new();
+    for signature in signatures {
+      if signature.signature_type != SignatureType::SchnorrPoseidon {
+        return Err(MinaMeshError::SignatureInvalid(format!(
+          "Expected SchnorrPoseidon, found {:?}",
+          signature.signature_type
+        )));
+      }
+      let signer = signature
+        .signing_payload
+        .account_identifier
+        .as_ref()
+        .map(|account_identifier| account_identifier.address.clone())
+        .or_else(|| signature.signing_payload.address.clone())
+        .ok_or(MinaMeshError::SignatureInvalid("Signing payload has no account identifier".to_string()))?;
+      if !signers.contains(&signer) {
+        return Err(MinaMeshError::SignatureInvalid(format!("{} is not a signer of the zkApp command", signer)));
+      }
+
+      let decoded_signature = decode_signature(signature.hex_bytes.as_str())?;
+      let signer_pk = PubKey::from_address(&signer)
+        .map_err(|e| MinaMeshError::PublicKeyFormatNotValid(format!("Signer pk decompression failed: {}", e)))?;
+      if !verify_zkapp_signature(&decoded_signature, &signer_pk, full_commitment, network_id) {
+        return Err(MinaMeshError::SignatureInvalid(format!(
+          "Signature does not verify against signer {} on network {}:{}",
+          signer, network_identifier.blockchain, network_identifier.network
+        )));
+      }
+      signatures_by_signer.insert(signer, signature.hex_bytes.clone());
+    }
+
+    let zkapp_command = zkapp_command.with_signatures(&signatures_by_signer)?;
+    let signed_transaction = TransactionSigned {
+      signature: zkapp_command.fee_payer.signature.clone().ok_or(MinaMeshError::SignatureMissing)?,
+      payment: None,
+      stake_delegation: None,
+      zkapp_command: Some(zkapp_command),
+    };
     let signed_transaction_json = signed_transaction.as_json_string()?;
 
     Ok(ConstructionCombineResponse::new(signed_transaction_json))
@@ -28,21 +28,34 @@ impl MinaMesh {
     self.validate_network(&request.network_identifier).await?;
 
     let tx: TransactionSigned = TransactionSigned::from_json_string(&request.signed_transaction)?;
-    self.check_transaction(&tx)?;
+    let hash = self.transaction_hash(&tx)?;
+
+    Ok(TransactionIdentifierResponse::new(TransactionIdentifier::new(hash)))
+  }
+
+  /// Validates the signed transaction and computes its hash.
+  pub(crate) fn transaction_hash(&self, tx: &TransactionSigned) -> Result<String, MinaMeshError> {
+    self.check_transaction(tx)?;
     decode_signature(&tx.signature)?;
 
-    let signer_pk = self.extract_signer(&tx)?;
+    if let Some(zkapp_command) = &tx.zkapp_command {
+      self.validate_zkapp_command(zkapp_command)?;
+      zkapp_command.check_signatures()?;
+      return zkapp_command.hash();
+    }
+
+    let signer_pk = self.extract_signer(tx)?;
     let signer = non_zero_curve_point_from_compressed(signer_pk.into_compressed());
 
     let user_command_payload = if let Some(payment) = &tx.payment {
       let operations = generate_operations_user_command(payment);
-      self.validate_operations(&tx, &operations, payment.valid_until, payment.memo.clone())?
+      self.validate_operations(tx, &operations, payment.valid_until, payment.memo.clone())?
     } else if let Some(stake_delegation) = &tx.stake_delegation {
       let operations = generate_operations_user_command(stake_delegation);
-      self.validate_operations(&tx, &operations, stake_delegation.valid_until, stake_delegation.memo.clone())?
+      self.validate_operations(tx, &operations, stake_delegation.valid_until, stake_delegation.memo.clone())?
     } else {
       return Err(MinaMeshError::JsonParse(Some(
-        "Signed transaction must have one of: payment, stake_delegation".to_string(),
+        "Signed transaction must have one of: payment, stake_delegation, zkapp_command".to_string(),
       )));
     };
 
@@ -54,7 +67,7 @@ impl MinaMesh {
 
     let hash = mina_base_signed.hash().map_err(|e| MinaMeshError::Exception(e.to_string()))?;
 
-    Ok(TransactionIdentifierResponse::new(TransactionIdentifier::new(hash.to_string())))
+    Ok(hash.to_string())
   }
 
   /// Extract and decompress the signer from the transaction.
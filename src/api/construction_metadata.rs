@@ -7,10 +7,18 @@ use crate::{
   create_currency,
   graphql::{Block3, PublicKey, QueryConstructionMetadata, QueryConstructionMetadataVariables, TokenId},
   signer_utils::validate_base58_with_checksum,
-  util::{DEFAULT_TOKEN_ID, MINIMUM_USER_COMMAND_FEE},
-  MinaMesh, MinaMeshError, TransactionMetadata,
+  util::{is_default_token, DEFAULT_TOKEN_ID, MINIMUM_USER_COMMAND_FEE},
+  MinaMesh, MinaMeshError, PartialReason, TransactionMetadata,
 };
 
+// Sent as a raw document, like `sendZkapp`, so the generated types of the
+// metadata query stay as they are.
+const QUERY_TOKEN_ACCOUNTS: &str = r#"
+query TokenAccounts($receiver: PublicKey!, $token: TokenId!) {
+  receiver: account(publicKey: $receiver, token: $token) { nonce }
+  tokenOwner(tokenId: $token) { publicKey permissions { access } }
+}"#;
+
 /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/app/rosetta/lib/construction.ml#L133
 impl MinaMesh {
   pub async fn construction_metadata(
@@ -59,12 +67,22 @@ impl MinaMesh {
     let best_chain = response.best_chain.ok_or(MinaMeshError::ChainInfoMissing)?;
     let suggested_fee = self.suggested_fee(best_chain).unwrap_or(MINIMUM_USER_COMMAND_FEE);
 
+    // Custom token transfers create the receiver's account of that token and
+    // are approved by the token owner.
+    let (receiver_exists, token_owner) = if is_default_token(token_id) {
+      (response.receiver.is_some(), None)
+    } else {
+      let (receiver_exists, token_owner) = self.query_token_accounts(receiver, token_id).await?;
+      (receiver_exists, Some(token_owner))
+    };
+
     // Construct metadata
-    let account_creation_fee = response.receiver.is_none().then_some(account_creation_fee_value.0);
+    let account_creation_fee = (!receiver_exists).then_some(account_creation_fee_value.0);
     let valid_until = options.get("valid_until").and_then(|v| v.as_str());
     let memo = options.get("memo").and_then(|v| v.as_str());
     let metadata =
-      TransactionMetadata::new(sender, receiver, inferred_nonce, token_id, account_creation_fee, valid_until, memo);
+      TransactionMetadata::new(sender, receiver, inferred_nonce, token_id, account_creation_fee, valid_until, memo)
+        .with_token_owner(token_owner);
 
     // Construct suggested fee
     let suggested_fee_entry = Amount {
@@ -84,6 +102,32 @@ impl MinaMesh {
     Ok(ConstructionMetadataResponse { metadata: metadata.to_json(), suggested_fee: Some(vec![suggested_fee_entry]) })
   }
 
+  /// Whether the receiver has an account of the token, and the token's owner.
+  /// The owner approves the transfer without authorization, so tokens whose
+  /// owner requires a proof or a signature to access its account are rejected.
+  async fn query_token_accounts(&self, receiver: &str, token_id: &str) -> Result<(bool, String), MinaMeshError> {
+    let variables = json!({ "receiver": receiver, "token": token_id });
+    let response = self
+      .graphql_client
+      .send(cynic::Operation::<Value, Value>::new(QUERY_TOKEN_ACCOUNTS.to_string(), variables))
+      .await?;
+
+    let token_owner = response
+      .pointer("/tokenOwner/publicKey")
+      .and_then(Value::as_str)
+      .ok_or(MinaMeshError::AccountNotFound(format!("Token owner not found for token: {}", token_id)))?;
+    match response.pointer("/tokenOwner/permissions/access").and_then(Value::as_str) {
+      None | Some("None") => {}
+      Some(access) => {
+        return Err(MinaMeshError::OperationsNotValid(vec![PartialReason::InvalidMetadata(format!(
+          "Token owner {} of token {} requires {} authorization, only tokens whose owner requires none are supported",
+          token_owner, token_id, access
+        ))]))
+      }
+    }
+    Ok((!response["receiver"].is_null(), token_owner.to_string()))
+  }
+
   fn get_field_from_options<'a>(&self, options: &'a Value, field: &'a str) -> Result<&'a str, MinaMeshError> {
     options
       .get(field)
@@ -6,8 +6,8 @@ use crate::{
   generate_operations_user_command,
   signer_utils::decode_signature,
   util::{DEFAULT_TOKEN_ID, MINIMUM_USER_COMMAND_FEE},
-  HasPaymentAndDelegation, MinaMesh, MinaMeshError, PartialUserCommand, TransactionSigned, TransactionUnsigned,
-  UserCommandPayload,
+  HasPaymentAndDelegation, MinaMesh, MinaMeshError, PartialUserCommand, PartialZkappCommand, TransactionSigned,
+  TransactionUnsigned, UserCommandPayload, ZkappCommandPayload,
 };
 
 /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/app/rosetta/lib/construction.ml#L615
@@ -18,7 +18,7 @@ impl MinaMesh {
   ) -> Result<ConstructionParseResponse, MinaMeshError> {
     self.validate_network(&request.network_identifier).await?;
 
-    let (mut operations, metadata, account_identifier) = if request.signed {
+    let (mut operations, metadata, account_identifiers) = if request.signed {
       // Parse signed transaction
       let tx = TransactionSigned::from_json_string(&request.transaction)?;
       self.check_transaction(&tx)?;
@@ -30,7 +30,7 @@ impl MinaMesh {
         let account_identifier = self.make_account_identifier(payment.from.clone(), payment.token.clone());
         let operations = generate_operations_user_command(payment);
         self.validate_operations(&tx, &operations, payment.valid_until, payment.memo.clone())?;
-        (operations, metadata, Some(account_identifier))
+        (operations, metadata, Some(vec![account_identifier]))
       } else if let Some(stake_delegation) = &tx.stake_delegation {
         self.check_fee(stake_delegation.fee)?;
         let metadata = self.make_metadata(stake_delegation.memo.clone(), stake_delegation.valid_until);
@@ -38,10 +38,21 @@ impl MinaMesh {
           self.make_account_identifier(stake_delegation.delegator.clone(), DEFAULT_TOKEN_ID.to_string());
         let operations = generate_operations_user_command(stake_delegation);
         self.validate_operations(&tx, &operations, stake_delegation.valid_until, stake_delegation.memo.clone())?;
-        (operations, metadata, Some(account_identifier))
+        (operations, metadata, Some(vec![account_identifier]))
+      } else if let Some(zkapp_command) = &tx.zkapp_command {
+        self.check_fee(zkapp_command.fee_payer.fee)?;
+        self.validate_zkapp_command(zkapp_command)?;
+        zkapp_command.check_signatures()?;
+        let metadata = self.make_metadata(zkapp_command.memo.clone(), zkapp_command.fee_payer.valid_until);
+        let account_identifiers = zkapp_command
+          .signers()
+          .into_iter()
+          .map(|signer| self.make_account_identifier(signer, DEFAULT_TOKEN_ID.to_string()))
+          .collect();
+        (zkapp_command.to_operations(), metadata, Some(account_identifiers))
       } else {
         return Err(MinaMeshError::JsonParse(Some(
-          "Signed transaction must have one of: payment, stake_delegation".to_string(),
+          "Signed transaction must have one of: payment, stake_delegation, zkapp_command".to_string(),
         )));
       }
     } else {
@@ -66,9 +77,14 @@ impl MinaMesh {
           stake_delegation.memo.clone(),
         )?;
         (operations, metadata, None)
+      } else if let Some(zkapp_command) = &tx.zkapp_command {
+        self.check_fee(zkapp_command.fee_payer.fee)?;
+        self.validate_unsigned_zkapp_command(&tx)?;
+        let metadata = self.make_metadata(zkapp_command.memo.clone(), zkapp_command.fee_payer.valid_until);
+        (zkapp_command.to_operations(), metadata, None)
       } else {
         return Err(MinaMeshError::JsonParse(Some(
-          "Signed transaction must have one of: payment, stake_delegation".to_string(),
+          "Signed transaction must have one of: payment, stake_delegation, zkapp_command".to_string(),
         )));
       }
     };
@@ -81,21 +97,19 @@ impl MinaMesh {
     Ok(ConstructionParseResponse {
       operations,
       signers: None,
-      account_identifier_signers: if account_identifier.is_some() {
-        Some(vec![account_identifier.unwrap()])
-      } else {
-        None
-      },
+      account_identifier_signers: account_identifiers,
       metadata,
     })
   }
 
   pub fn check_transaction<T: HasPaymentAndDelegation>(&self, tx: &T) -> Result<(), MinaMeshError> {
-    if tx.payment().is_some() && tx.stake_delegation().is_some()
-      || tx.payment().is_none() && tx.stake_delegation().is_none()
-    {
+    let commands = [tx.payment().is_some(), tx.stake_delegation().is_some(), tx.zkapp_command().is_some()]
+      .iter()
+      .filter(|c| **c)
+      .count();
+    if commands != 1 {
       return Err(MinaMeshError::JsonParse(Some(
-        "Signed transaction must have one of: payment, stake_delegation".to_string(),
+        "Signed transaction must have one of: payment, stake_delegation, zkapp_command".to_string(),
       )));
     }
     Ok(())
@@ -133,7 +147,7 @@ impl MinaMesh {
     operations: &[Operation],
     valid_until: Option<u32>,
     memo: Option<String>,
-  ) -> Result<(), MinaMeshError> {
+  ) -> Result<UserCommandPayload, MinaMeshError> {
     let request_tx = tx.clone();
 
     let user_command_payload = self.validate_operations(tx, operations, valid_until, memo)?;
@@ -143,6 +157,36 @@ impl MinaMesh {
         "Unsigned transaction does not match operations, randomOracleInput or signerInput".to_string(),
       )));
     }
+    Ok(user_command_payload)
+  }
+
+  pub fn validate_unsigned_zkapp_command(
+    &self,
+    tx: &TransactionUnsigned,
+  ) -> Result<ZkappCommandPayload, MinaMeshError> {
+    let zkapp_command = tx
+      .zkapp_command
+      .as_ref()
+      .ok_or(MinaMeshError::JsonParse(Some("Unsigned transaction has no zkapp_command".to_string())))?;
+    self.validate_zkapp_command(zkapp_command)?;
+    if *tx != TransactionUnsigned::from(&zkapp_command.unsigned()) {
+      return Err(MinaMeshError::JsonParse(Some(
+        "Unsigned transaction does not match operations, randomOracleInput or signerInput".to_string(),
+      )));
+    }
+    Ok(zkapp_command.clone())
+  }
+
+  /// Rebuilds the zkApp command from its operations and checks that it is the
+  /// command that was handed in, signatures aside.
+  pub fn validate_zkapp_command(&self, zkapp_command: &ZkappCommandPayload) -> Result<(), MinaMeshError> {
+    let valid_until = zkapp_command.fee_payer.valid_until.map(|v| v.to_string());
+    let partial_zkapp_command =
+      PartialZkappCommand::from_operations(&zkapp_command.to_operations(), valid_until, zkapp_command.memo.clone())?;
+    if partial_zkapp_command.to_zkapp_command_payload(zkapp_command.fee_payer.nonce, None)? != zkapp_command.unsigned()
+    {
+      return Err(MinaMeshError::JsonParse(Some("zkApp command does not match its operations".to_string())));
+    }
     Ok(())
   }
 
@@ -1,11 +1,17 @@
 use anyhow::Result;
 use coinbase_mesh::models::{
-  AccountIdentifier, ConstructionPayloadsRequest, ConstructionPayloadsResponse, SignatureType, SigningPayload,
+  AccountIdentifier, ConstructionPayloadsRequest, ConstructionPayloadsResponse, Operation, SignatureType,
+  SigningPayload,
 };
 use mina_signer::CompressedPubKey;
 use serde_json::json;
 
-use crate::{MinaMesh, MinaMeshError, PartialUserCommand, TransactionMetadata, TransactionUnsigned};
+use crate::{
+  is_zkapp_operations,
+  util::{is_default_token, DEFAULT_TOKEN_ID},
+  MinaMesh, MinaMeshError, PartialReason, PartialUserCommand, PartialZkappCommand, TransactionMetadata,
+  TransactionUnsigned,
+};
 
 /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/app/rosetta/lib/construction.ml#L473
 impl MinaMesh {
@@ -21,6 +27,10 @@ impl MinaMesh {
       .ok_or(MinaMeshError::JsonParse(Some("Metadata is required for payloads request".to_string())))?
       .try_into()?;
 
+    if is_zkapp_operations(&request.operations) {
+      return self.zkapp_command_payloads(&request.operations, metadata);
+    }
+
     // Create a partial user command from the operations
     let partial_user_command =
       PartialUserCommand::from_operations(&request.operations, metadata.valid_until, metadata.memo)?;
@@ -58,4 +68,54 @@ impl MinaMesh {
 
     Ok(ConstructionPayloadsResponse::new(unsigned_transaction_json, vec![signing_payload]))
   }
+
+  /// Every signer of a zkApp command (the fee payer and each debited account)
+  /// signs the same full commitment, so one signing payload is returned per
+  /// distinct signer.
+  fn zkapp_command_payloads(
+    &self,
+    operations: &[Operation],
+    metadata: TransactionMetadata,
+  ) -> Result<ConstructionPayloadsResponse, MinaMeshError> {
+    let partial_zkapp_command = PartialZkappCommand::from_operations(operations, metadata.valid_until, metadata.memo)?;
+
+    // Unlike MINA accounts, new custom token accounts cannot pay their creation
+    // fee out of the received amount.
+    if !is_default_token(&metadata.token_id)
+      && metadata.account_creation_fee.is_some()
+      && !partial_zkapp_command.pays_account_creation_fee()
+    {
+      return Err(MinaMeshError::OperationsNotValid(vec![PartialReason::InvalidMetadata(format!(
+        "Receiver {} has no account for token {}, an account_creation_fee_via_payment operation is required",
+        metadata.receiver, metadata.token_id
+      ))]));
+    }
+
+    let nonce_u32 = metadata
+      .nonce
+      .parse::<u32>()
+      .map_err(|_| MinaMeshError::JsonParse(Some(format!("Invalid nonce: {}", metadata.nonce))))?;
+    let zkapp_command_payload =
+      partial_zkapp_command.to_zkapp_command_payload(nonce_u32, metadata.token_owner.as_deref())?;
+
+    let unsigned_transaction: TransactionUnsigned = (&zkapp_command_payload).into();
+    let unsigned_transaction_json = unsigned_transaction.as_json_string()?;
+
+    let hex_bytes = zkapp_command_payload.signing_payload_hex()?;
+    let signing_payloads = zkapp_command_payload
+      .signers()
+      .into_iter()
+      .map(|address| SigningPayload {
+        account_identifier: Some(
+          AccountIdentifier { address, metadata: Some(json!({ "token_id": DEFAULT_TOKEN_ID })), sub_account: None }
+            .into(),
+        ),
+        hex_bytes: hex_bytes.clone(),
+        signature_type: Some(SignatureType::SchnorrPoseidon),
+        address: None,
+      })
+      .collect();
+
+    Ok(ConstructionPayloadsResponse::new(unsigned_transaction_json, signing_payloads))
+  }
 }
@@ -3,7 +3,8 @@ use coinbase_mesh::models::{ConstructionPreprocessRequest, ConstructionPreproces
 use serde_json::{json, Map, Value};
 
 use crate::{
-  signer_utils::validate_base58_with_checksum, MinaMesh, MinaMeshError, PartialUserCommand, PreprocessMetadata,
+  is_zkapp_operations, signer_utils::validate_base58_with_checksum, MinaMesh, MinaMeshError, PartialUserCommand,
+  PartialZkappCommand, PreprocessMetadata,
 };
 
 impl MinaMesh {
@@ -14,6 +15,22 @@ impl MinaMesh {
     self.validate_network(&request.network_identifier).await?;
 
     let metadata = PreprocessMetadata::from_json(request.metadata)?.unwrap_or_default();
+    if is_zkapp_operations(&request.operations) {
+      let partial_command =
+        PartialZkappCommand::from_operations(&request.operations, metadata.valid_until, metadata.memo)?;
+      validate_base58_public_key(partial_command.fee_payer.as_str())?;
+      for account_update in &partial_command.account_updates {
+        validate_base58_public_key(account_update.public_key.as_str())?;
+        if let Some(token_owner) = &account_update.token_owner {
+          validate_base58_public_key(token_owner.as_str())?;
+        }
+      }
+      return Ok(ConstructionPreprocessResponse {
+        options: Some(make_zkapp_response_options(partial_command)),
+        required_public_keys: Some(vec![]),
+      });
+    }
+
     let partial_command =
       PartialUserCommand::from_operations(&request.operations, metadata.valid_until, metadata.memo)?;
 
@@ -46,6 +63,33 @@ fn make_response_options(partial_command: PartialUserCommand) -> Value {
   json!(options)
 }
 
+// The fee payer's nonce is all /construction/metadata needs to look up, so the
+// options mirror those of a payment from the fee payer to the first credited
+// account.
+fn make_zkapp_response_options(partial_command: PartialZkappCommand) -> Value {
+  let receiver = partial_command
+    .account_updates
+    .iter()
+    .find(|account_update| !account_update.requires_signature())
+    .unwrap_or(&partial_command.account_updates[0]);
+
+  let mut options = Map::new();
+
+  options.insert("sender".to_string(), json!(partial_command.fee_payer));
+  options.insert("receiver".to_string(), json!(receiver.public_key));
+  options.insert("token_id".to_string(), json!(receiver.token_id));
+
+  if let Some(valid_until) = partial_command.valid_until {
+    options.insert("valid_until".to_string(), json!(valid_until));
+  }
+
+  if let Some(memo) = partial_command.memo {
+    options.insert("memo".to_string(), json!(memo));
+  }
+
+  json!(options)
+}
+
 fn validate_base58_public_key(pk: &str) -> Result<(), MinaMeshError> {
   validate_base58_with_checksum(pk, None).map_err(|e| MinaMeshError::PublicKeyFormatNotValid(e.to_string()))
 }
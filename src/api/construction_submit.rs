@@ -1,12 +1,21 @@
+use std::sync::Arc;
+
 use anyhow::Result;
 use coinbase_mesh::models::{ConstructionSubmitRequest, TransactionIdentifier};
 use cynic::MutationBuilder;
+use serde_json::{json, Value};
 
 use crate::{
   graphql::{SendDelegation, SendDelegationVariables, SendPayment, SendPaymentVariables},
-  MinaMesh, MinaMeshError, Payment, StakeDelegation, TransactionSigned,
+  util::is_default_token,
+  MinaMesh, MinaMeshError, PartialReason, Payment, Reservation, StakeDelegation, SubmissionStore, TransactionSigned,
+  ZkappCommandPayload,
 };
 
+// Sent as a raw document: the zkApp command input is built as JSON rather than
+// through generated cynic types.
+const SEND_ZKAPP: &str = "mutation SendZkapp($input: SendZkappInput!) { sendZkapp(input: $input) { zkapp { hash } } }";
+
 /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/app/rosetta/lib/construction.ml#L849
 impl MinaMesh {
   pub async fn construction_submit(
@@ -16,32 +25,70 @@ impl MinaMesh {
     self.validate_network(&request.network_identifier).await?;
 
     let signed_transaction = TransactionSigned::from_json_string(&request.signed_transaction)?;
-    if signed_transaction.payment.is_some() && signed_transaction.stake_delegation.is_some() {
-      return Err(MinaMeshError::JsonParse(Some(
-        "Signed transaction must have one of: payment, stake_delegation".to_string(),
-      )));
-    }
+    self.check_transaction(&signed_transaction)?;
+
+    // Transactions whose hash cannot be computed are left to the daemon to
+    // reject, with the more specific error it reports.
+    let hash = match self.transaction_hash(&signed_transaction) {
+      Ok(hash) => Some(hash),
+      Err(e) => {
+        tracing::debug!("Failed to compute the transaction hash: {}", e);
+        None
+      }
+    };
+    // Reserved before broadcasting, so that a concurrent submission of the same
+    // transaction is not broadcast twice
+    let reservation = match hash {
+      Some(hash) => {
+        let submissions = self.submissions.clone();
+        match run_blocking(move || ReservedSubmission::reserve(submissions, hash)).await? {
+          Ok(reserved) => Some(reserved),
+          Err(Reservation::Submitted(transaction_identifier)) => {
+            tracing::info!("Transaction {} was already submitted", transaction_identifier);
+            return Ok(TransactionIdentifier::new(transaction_identifier));
+          }
+          Err(_) => {
+            return Err(MinaMeshError::TransactionSubmitDuplicate("Transaction is already being submitted".to_string()))
+          }
+        }
+      }
+      None => None,
+    };
 
-    // tracing::debug!("CACHE: {:?}", self.cache);
-    if signed_transaction.payment.is_some() {
+    let transaction_identifier = if let Some(payment) = signed_transaction.payment {
       tracing::info!("Payment transaction");
-      let payment = signed_transaction.payment.unwrap();
-      let hash = self.send_payment(payment, &signed_transaction.signature).await?;
-      self.cache_transaction(&signed_transaction.signature);
-      tracing::info!("Success! Transaction hash: {}", hash);
-      Ok(TransactionIdentifier::new(hash))
-    } else if signed_transaction.stake_delegation.is_some() {
+      if !is_default_token(&payment.token) {
+        return Err(MinaMeshError::OperationsNotValid(vec![PartialReason::InvalidMetadata(format!(
+          "Token {} can only be transferred with zkapp_balance_update operations",
+          payment.token
+        ))]));
+      }
+      self.send_payment(payment, &signed_transaction.signature).await?
+    } else if let Some(delegation) = signed_transaction.stake_delegation {
       tracing::info!("Stake delegation transaction");
-      let delegation = signed_transaction.stake_delegation.unwrap();
-      let hash = self.send_delegation(delegation, &signed_transaction.signature).await?;
-      self.cache_transaction(&signed_transaction.signature);
-      tracing::info!("Success! Transaction hash: {}", hash);
-
-      Ok(TransactionIdentifier::new(hash.to_string()))
+      self.send_delegation(delegation, &signed_transaction.signature).await?
+    } else if let Some(zkapp_command) = signed_transaction.zkapp_command {
+      tracing::info!("zkApp command transaction");
+      self.validate_zkapp_command(&zkapp_command)?;
+      zkapp_command.check_signatures()?;
+      self.send_zkapp(zkapp_command).await?
     } else {
-      tracing::debug!("Signed transaction missing payment or stake delegation");
-      return Err(MinaMeshError::JsonParse(Some("Signed transaction missing payment or stake delegation".to_string())));
+      tracing::debug!("Signed transaction missing payment, stake delegation or zkApp command");
+      return Err(MinaMeshError::JsonParse(Some(
+        "Signed transaction missing payment, stake delegation or zkApp command".to_string(),
+      )));
+    };
+    tracing::info!("Success! Transaction hash: {}", transaction_identifier);
+
+    if let Some(reserved) = reservation {
+      // The daemon accepted the transaction: failing to record it only weakens
+      // the duplicate guard, so the submission still succeeds.
+      let recorded = transaction_identifier.clone();
+      if let Err(e) = run_blocking(move || reserved.insert(&recorded)).await {
+        tracing::warn!("Failed to record the submitted transaction {}: {}", transaction_identifier, e);
+      }
     }
+    Ok(TransactionIdentifier::new(transaction_identifier))
   }
 
   async fn send_payment(&self, payment: Payment, signature: &str) -> Result<String, MinaMeshError> {
@@ -61,7 +108,7 @@ impl MinaMesh {
 
     match response {
       Ok(response) => Ok(response.send_payment.payment.hash.0),
-      Err(err) => Err(self.map_error(err, signature, Some(payment_clone)).await),
+      Err(err) => Err(self.map_error(err, Some(payment_clone)).await),
     }
   }
 
@@ -80,11 +127,27 @@ impl MinaMesh {
 
     match response {
       Ok(response) => Ok(response.send_delegation.delegation.hash.0),
-      Err(err) => Err(self.map_error(err, signature, None).await),
+      Err(err) => Err(self.map_error(err, None).await),
+    }
+  }
+
+  async fn send_zkapp(&self, zkapp_command: ZkappCommandPayload) -> Result<String, MinaMeshError> {
+    let variables = json!({ "input": { "zkappCommand": zkapp_command.to_graphql_input()? } });
+    let operation = cynic::Operation::<Value, Value>::new(SEND_ZKAPP.to_string(), variables);
+
+    let response = self.graphql_client.send(operation).await;
+
+    match response {
+      Ok(response) => response
+        .pointer("/sendZkapp/zkapp/hash")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or(MinaMeshError::GraphqlMinaQuery("sendZkapp response is missing the zkApp hash".to_string())),
+      Err(err) => Err(self.map_error(err, None).await),
     }
   }
 
-  async fn map_error(&self, err: MinaMeshError, signed_tx_str: &str, payment: Option<Payment>) -> MinaMeshError {
+  async fn map_error(&self, err: MinaMeshError, payment: Option<Payment>) -> MinaMeshError {
     match err {
       MinaMeshError::GraphqlMinaQuery(err) => {
         if err.contains("Couldn't infer nonce") {
@@ -94,10 +157,6 @@ impl MinaMesh {
         } else if err.contains("Invalid_signature") {
           MinaMeshError::TransactionSubmitInvalidSignature(err)
         } else if err.contains("below minimum_nonce") {
-          if self.is_transaction_cached(signed_tx_str) {
-            return MinaMeshError::TransactionSubmitDuplicate(err);
-          }
-
           if let Some(payment) = payment {
             if self.is_transaction_in_db(payment).await.unwrap_or(false) {
               return MinaMeshError::TransactionSubmitDuplicate("Transaction already in database".to_string());
@@ -130,3 +189,39 @@ impl MinaMesh {
     Ok(row.is_some())
   }
 }
+
+/// A transaction hash reserved in the [SubmissionStore], released once dropped
+/// unless the transaction was recorded, including when the request is
+/// cancelled.
+struct ReservedSubmission {
+  submissions: Arc<dyn SubmissionStore>,
+  hash: String,
+}
+
+impl ReservedSubmission {
+  /// Reserves the hash, or returns why it cannot be.
+  fn reserve(submissions: Arc<dyn SubmissionStore>, hash: String) -> Result<Result<Self, Reservation>, MinaMeshError> {
+    Ok(match submissions.reserve(&hash)? {
+      Reservation::Reserved => Ok(Self { submissions, hash }),
+      reservation => Err(reservation),
+    })
+  }
+
+  fn insert(self, transaction_identifier: &str) -> Result<(), MinaMeshError> {
+    self.submissions.insert(&self.hash, transaction_identifier)
+  }
+}
+
+impl Drop for ReservedSubmission {
+  fn drop(&mut self) {
+    self.submissions.release(&self.hash);
+  }
+}
+
+/// Runs a [SubmissionStore] operation, which may block on I/O, off the async
+/// runtime.
+async fn run_blocking<T: Send + 'static>(
+  operation: impl FnOnce() -> Result<T, MinaMeshError> + Send + 'static,
+) -> Result<T, MinaMeshError> {
+  tokio::task::spawn_blocking(operation).await.map_err(|e| MinaMeshError::Exception(e.to_string()))?
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use coinbase_mesh::models::{BlockEvent, BlockEventType, BlockIdentifier, EventsBlocksRequest, EventsBlocksResponse};
+
+use crate::{util::MAX_BLOCK_EVENTS_LIMIT, MinaMesh, MinaMeshError};
+
+/// https://docs.cdp.coinbase.com/mesh/reference/eventsblocks
+impl MinaMesh {
+  /// The events of the canonical chain, whose sequence numbers are the heights
+  /// of their blocks. Pending blocks are left out until they become canonical,
+  /// so no event is ever removed. Without an `offset`, the latest `limit`
+  /// events are returned.
+  pub async fn events_blocks(&self, request: EventsBlocksRequest) -> Result<EventsBlocksResponse, MinaMeshError> {
+    self.validate_network(&request.network_identifier).await?;
+    let limit = request.limit.unwrap_or(100).clamp(0, MAX_BLOCK_EVENTS_LIMIT);
+
+    let max_sequence = sqlx::query_file!("sql/queries/max_canonical_height.sql")
+      .fetch_one(&self.pg_pool)
+      .await?
+      .max_canonical_height
+      .unwrap_or(0);
+    let offset = request.offset.unwrap_or(max_sequence - limit + 1).max(0);
+    let events = sqlx::query_file!("sql/queries/block_events.sql", offset, limit)
+      .fetch_all(&self.pg_pool)
+      .await?
+      .into_iter()
+      .map(|event| BlockEvent {
+        sequence: event.sequence,
+        block_identifier: Box::new(BlockIdentifier::new(event.sequence, event.state_hash)),
+        r#type: BlockEventType::BlockAdded,
+      })
+      .collect();
+
+    Ok(EventsBlocksResponse::new(max_sequence, events))
+  }
+}
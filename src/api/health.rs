@@ -0,0 +1,77 @@
+use coinbase_mesh::models::SyncStatus;
+use cynic::QueryBuilder;
+
+use crate::{graphql::QueryNetworkStatus, DependencyHealth, MinaMesh, MinaMeshError, ReadinessReport, TipDrift};
+
+impl MinaMesh {
+  /// Checks that the archive and the daemon are reachable, that the daemon is
+  /// synced and that the archive keeps up with the daemon's best tip. Offline
+  /// mode has no dependencies and is always ready, archive-only mode only
+  /// depends on the archive.
+  pub async fn health_ready(&self) -> ReadinessReport {
+    if self.offline {
+      return ReadinessReport { ready: true, archive: None, daemon: None, tip_drift: None };
+    }
+    if self.archive_only {
+      let archive = self.archive_health().await;
+      return ReadinessReport { ready: archive.healthy, archive: Some(archive), daemon: None, tip_drift: None };
+    }
+    let (archive, daemon) = tokio::join!(self.archive_health(), self.daemon_health());
+    let drift = match (archive.height, daemon.height) {
+      (Some(archive_height), Some(daemon_height)) => Some(daemon_height - archive_height),
+      _ => None,
+    };
+    let tip_drift = TipDrift {
+      healthy: drift.is_some_and(|drift| drift <= self.max_tip_drift as i64),
+      drift,
+      max_drift: self.max_tip_drift,
+    };
+    ReadinessReport {
+      ready: archive.healthy && daemon.healthy && tip_drift.healthy,
+      archive: Some(archive),
+      daemon: Some(daemon),
+      tip_drift: Some(tip_drift),
+    }
+  }
+
+  /// The archive is healthy once it has a canonical block. Its height is that
+  /// of its highest block whatever the chain status, as the canonical chain
+  /// trails the daemon's best tip by the finality depth.
+  async fn archive_health(&self) -> DependencyHealth {
+    let heights = tokio::try_join!(
+      sqlx::query_file!("sql/queries/max_canonical_height.sql").fetch_one(&self.pg_pool),
+      sqlx::query_file!("sql/queries/max_height.sql").fetch_one(&self.pg_pool),
+    );
+    match heights {
+      Ok((canonical, tip)) => match (canonical.max_canonical_height, tip.max_height) {
+        (Some(_), Some(height)) => DependencyHealth::healthy(height),
+        _ => DependencyHealth::unhealthy("Archive has no canonical block"),
+      },
+      Err(err) => DependencyHealth::unhealthy(MinaMeshError::from(err)),
+    }
+  }
+
+  async fn daemon_health(&self) -> DependencyHealth {
+    let QueryNetworkStatus { best_chain, sync_status, .. } =
+      match self.graphql_client.send(QueryNetworkStatus::build(())).await {
+        Ok(response) => response,
+        Err(err) => return DependencyHealth::unhealthy(err),
+      };
+    let height = best_chain
+      .as_ref()
+      .and_then(|blocks| blocks.first())
+      .and_then(|block| block.protocol_state.consensus_state.block_height.0.parse::<i64>().ok());
+    let SyncStatus { stage, synced, .. } = sync_status.into();
+    let synced = synced.unwrap_or_default();
+    DependencyHealth {
+      healthy: synced && height.is_some(),
+      height,
+      sync_status: stage,
+      error: match (synced, height) {
+        (false, _) => Some("Daemon is not synced".to_string()),
+        (true, None) => Some(MinaMeshError::ChainInfoMissing.to_string()),
+        (true, Some(_)) => None,
+      },
+    }
+  }
+}
@@ -15,13 +15,12 @@ impl MinaMesh {
     request: MempoolTransactionRequest,
   ) -> Result<MempoolTransactionResponse, MinaMeshError> {
     self.validate_network(&request.network_identifier).await?;
-    let QueryMempoolTransactions { daemon_status: _daemon_status, initial_peers: _initial_peers, pooled_user_commands } =
-      self
-        .graphql_client
-        .send(QueryMempoolTransactions::build(QueryMempoolTransactionsVariables {
-          hashes: Some(vec![request.transaction_identifier.hash.as_str()]),
-        }))
-        .await?;
+    let QueryMempoolTransactions { pooled_user_commands, .. } = self
+      .graphql_client
+      .send(QueryMempoolTransactions::build(QueryMempoolTransactionsVariables {
+        hashes: Some(vec![request.transaction_identifier.hash.as_str()]),
+      }))
+      .await?;
 
     // Check if the transaction is absent
     if pooled_user_commands.is_empty() {
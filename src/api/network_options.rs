@@ -22,7 +22,7 @@ impl MinaMesh {
         errors,
         historical_balance_lookup: true,
         timestamp_start_index: None,
-        call_methods: vec![],
+        call_methods: vec!["transaction_status".to_string()],
         balance_exemptions: vec![],
         mempool_coins: false,
         block_hash_case: Some(Some(Case::CaseSensitive)),
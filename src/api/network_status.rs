@@ -12,6 +12,9 @@ use crate::{
 impl MinaMesh {
   pub async fn network_status(&self, req: NetworkRequest) -> Result<NetworkStatusResponse, MinaMeshError> {
     self.validate_network(&req.network_identifier).await?;
+    if self.archive_only {
+      return self.archive_network_status().await;
+    }
     let QueryNetworkStatus { best_chain, daemon_status: DaemonStatus3 { peers }, sync_status } =
       self.graphql_client.send(QueryNetworkStatus::build(())).await?;
     let blocks = best_chain.ok_or(MinaMeshError::ChainInfoMissing)?;
@@ -30,4 +33,22 @@ impl MinaMesh {
       sync_status: Some(Box::new(sync_status.into())),
     })
   }
+
+  /// The status of the Archive Database alone: its best canonical block stands
+  /// in for the daemon's best tip, with neither peers nor a sync status.
+  async fn archive_network_status(&self) -> Result<NetworkStatusResponse, MinaMeshError> {
+    let (best_block, oldest_block) = tokio::try_join!(
+      sqlx::query_file!("sql/queries/best_canonical_block.sql").fetch_optional(&self.pg_pool),
+      sqlx::query_file!("sql/queries/oldest_block.sql").fetch_one(&self.pg_pool),
+    )?;
+    let best_block = best_block.ok_or(MinaMeshError::ChainInfoMissing)?;
+    Ok(NetworkStatusResponse {
+      peers: None,
+      current_block_identifier: Box::new(BlockIdentifier::new(best_block.height, best_block.state_hash)),
+      current_block_timestamp: best_block.timestamp.parse::<i64>()?,
+      genesis_block_identifier: Box::new(self.genesis_block_identifier.clone()),
+      oldest_block_identifier: Some(Box::new(BlockIdentifier::new(oldest_block.height, oldest_block.state_hash))),
+      sync_status: None,
+    })
+  }
 }
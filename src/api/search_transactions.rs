@@ -1,86 +1,248 @@
+use std::{
+  cmp::Reverse,
+  str::FromStr,
+  time::{Duration, Instant},
+};
+
 use coinbase_mesh::models::{
-  BlockIdentifier, BlockTransaction, SearchTransactionsRequest, SearchTransactionsResponse, Transaction,
-  TransactionIdentifier,
+  BlockIdentifier, BlockTransaction, Currency, Operator, SearchTransactionsRequest, SearchTransactionsResponse,
+  Transaction, TransactionIdentifier,
 };
+use sqlx::PgPool;
 
 use crate::{
   generate_internal_command_transaction_identifier, generate_operations_internal_command,
-  generate_operations_user_command, generate_operations_zkapp_command, generate_transaction_metadata, ChainStatus,
-  HasTimestamp, InternalCommand, InternalCommandType, MinaMesh, MinaMeshError, TransactionStatus, UserCommand,
-  UserCommandType, ZkAppCommand,
+  generate_operations_user_command, generate_operations_zkapp_command, generate_transaction_metadata,
+  util::{
+    begin_archive_transaction, is_default_token, with_statement_timeout, DEFAULT_TOKEN_ID,
+    MAX_SEARCH_TRANSACTIONS_LIMIT,
+  },
+  ChainStatus, CommandKind, HasTimestamp, InternalCommand, InternalCommandType, MinaMesh, MinaMeshError, OperationType,
+  SearchCounts, SearchPool, SearchTransactionsCursor, SearchTransactionsMetadata, SearchTransactionsPageRequest,
+  SearchTransactionsPageResponse, SortOrder, TransactionStatus, UserCommand, UserCommandType, ZkAppCommand,
 };
 
+/// How long the choice between the read replica and the Archive Database, and
+/// the highest canonical block of the chosen one, are relied upon.
+const SEARCH_POOL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 impl MinaMesh {
   pub async fn search_transactions(
     &self,
     req: SearchTransactionsRequest,
   ) -> Result<SearchTransactionsResponse, MinaMeshError> {
-    self.validate_network(&req.network_identifier).await?;
-    let original_offset = req.offset.unwrap_or(0);
-    let mut offset = original_offset;
-    let mut limit = req.limit.unwrap_or(100);
-    let mut transactions = Vec::new();
-    let mut total_count = 0;
-    tracing::debug!("{:?}", req);
-    tracing::debug!("Offset: {}, Limit: {}", offset, limit);
+    Ok(self.search_transactions_page(req.into()).await?.response)
+  }
+
+  /// Pages by `offset` unless the request holds a `cursor` or an `order`, in
+  /// which case the page follows the cursor, if any, in the global order of
+  /// results, up to the highest canonical block, and ends with the next cursor.
+  /// Results are in that global order, ascending unless given otherwise, if
+  /// paged by cursor. A walk is caught up once a page is empty, and resumes
+  /// from its last cursor as canonical blocks are added.
+  pub async fn search_transactions_page(
+    &self,
+    req: SearchTransactionsPageRequest,
+  ) -> Result<SearchTransactionsPageResponse, MinaMeshError> {
+    self.validate_network(&req.request.network_identifier).await?;
+    let timeout = self.statement_timeouts.search_transactions;
+    let metadata = req.metadata.unwrap_or_default();
+    let order = metadata.order.unwrap_or_default();
+    if req.cursor.is_none() && metadata.order.is_none() {
+      let response = with_statement_timeout(timeout, self.search_archive_transactions(req.request, &metadata)).await?;
+      return Ok(SearchTransactionsPageResponse { response, next_cursor: None });
+    }
+    // Sorting is left to the database by paging by cursor, as sorting the
+    // commands of all kinds up to an offset would fetch all of them
+    if req.request.offset.is_some_and(|offset| offset != 0) {
+      return Err(MinaMeshError::InvalidCursor(match req.cursor {
+        Some(_) => "A cursor cannot be combined with an offset".to_string(),
+        None => "Sorted results are paged by cursor, not by offset".to_string(),
+      }));
+    }
+    // No cursor, or an empty one, starts from the first result in the order
+    let cursor = req.cursor.unwrap_or_default();
+    let cursor = if cursor.is_empty() { None } else { Some(SearchTransactionsCursor::decode(&cursor)?) };
+    let mut request = req.request;
+    with_statement_timeout(timeout, async {
+      // Pending blocks above the highest canonical block may still be added or
+      // orphaned, so the walk stops at the latter
+      let (pg_pool, max_canonical_height) = self.search_pg_pool().await?;
+      request.max_block = Some(request.max_block.map_or(max_canonical_height, |max| max.min(max_canonical_height)));
+      self.search_archive_transactions_sorted(pg_pool, request, &metadata, order, cursor).await
+    })
+    .await
+  }
 
-    let query_params = SearchTransactionsQueryParams::try_from(req.clone())?;
+  /// The results in the global order of [SearchTransactionsCursor], after the
+  /// cursor if given. The next cursor is that of the last result, if any.
+  async fn search_archive_transactions_sorted(
+    &self,
+    pg_pool: &PgPool,
+    req: SearchTransactionsRequest,
+    metadata: &SearchTransactionsMetadata,
+    order: SortOrder,
+    cursor: Option<SearchTransactionsCursor>,
+  ) -> Result<SearchTransactionsPageResponse, MinaMeshError> {
+    let limit = req.limit.unwrap_or(100).clamp(0, MAX_SEARCH_TRANSACTIONS_LIMIT);
+    let mut query_params = SearchTransactionsQueryParams::try_from(req.clone())?.with_metadata(metadata);
+    query_params.descending = Some(order == SortOrder::Desc);
     let include_timestamp = req.include_timestamp.unwrap_or(false);
+    tracing::debug!("Cursor: {:?}, Order: {:?}, Limit: {}", cursor, order, limit);
+
+    // Any of the kinds may hold all commands of the page, so each is queried
+    // for as many commands.
+    let cursor = cursor.as_ref();
+    let cached_counts = self.cache.search_counts.get(&query_params.counts_key());
+    let count = cached_counts.is_none();
+    let (user_commands, internal_commands, zkapp_commands) = tokio::try_join!(
+      self.fetch_user_commands(pg_pool, &query_params, cursor, count, 0, limit),
+      self.fetch_internal_commands(pg_pool, &query_params, cursor, count, 0, limit),
+      self.fetch_zkapp_commands(pg_pool, &query_params, cursor, count, 0, limit),
+    )?;
+
+    // The total counts disregard the cursor, but are only returned along with
+    // commands after it, so cached ones are reused.
+    let total_count = match cached_counts {
+      Some(counts) => counts.total(),
+      None => {
+        let counts = self
+          .count_search(
+            pg_pool,
+            &query_params,
+            user_commands.first().map(|uc| uc.total_count.unwrap_or(0)),
+            internal_commands.first().map(|ic| ic.total_count.unwrap_or(0)),
+            zkapp_commands.first().map(|zc| zc.total_count.unwrap_or(0)),
+          )
+          .await?;
+        self.cache.search_counts.insert(query_params.counts_key(), counts);
+        counts.total()
+      }
+    };
 
-    // User Commands
-    let user_commands = self.fetch_user_commands(&query_params, offset, limit).await?;
-    let user_commands_total_count = user_commands.first().and_then(|uc| uc.total_count).unwrap_or(0);
-    let user_transactions_bt: Vec<BlockTransaction> = map_to_block_transactions(user_commands, include_timestamp);
-    transactions.extend(user_transactions_bt);
-    total_count += user_commands_total_count;
-    tracing::debug!("User commands total: {}, retrieved: {}", user_commands_total_count, transactions.len());
-
-    // Internal Commands
-    let mut internal_commands_bt_len = 0;
-    if limit > transactions.len() as i64 {
-      // if we are below the limit, fetch internal commands
-      (offset, limit) = adjust_limit_and_offset(limit, offset, transactions.len() as i64);
-      tracing::debug!("Offset: {}, Limit: {}", offset, limit);
-      let internal_commands = self.fetch_internal_commands(&query_params, offset, limit).await?;
-      let internal_commands_total_count = internal_commands.first().and_then(|ic| ic.total_count).unwrap_or(0);
-      let internal_commands_bt: Vec<BlockTransaction> = map_to_block_transactions(internal_commands, include_timestamp);
-      internal_commands_bt_len = internal_commands_bt.len();
-      transactions.extend(internal_commands_bt);
-      total_count += internal_commands_total_count;
-      tracing::debug!(
-        "Internal commands total: {}, retrieved: {}",
-        internal_commands_total_count,
-        internal_commands_bt_len
-      );
-    } else {
-      // otherwise only fetch the first internal command to get the total count
-      let internal_commands = self.fetch_internal_commands(&query_params, 0, 1).await?;
-      let internal_commands_total_count = internal_commands.first().and_then(|ic| ic.total_count).unwrap_or(0);
-      total_count += internal_commands_total_count;
-      tracing::debug!("Internal commands total: {}", internal_commands_total_count);
+    let mut transactions = Vec::new();
+    transactions.extend(
+      user_commands
+        .into_iter()
+        .map(|command| (SearchTransactionsCursor::from(&command), to_block_transaction(command, include_timestamp))),
+    );
+    transactions.extend(
+      internal_commands
+        .into_iter()
+        .map(|command| (SearchTransactionsCursor::from(&command), to_block_transaction(command, include_timestamp))),
+    );
+    // A zkApp command spans a row per account update
+    let mut zkapp_command_rows: Vec<(SearchTransactionsCursor, Vec<ZkAppCommand>)> = Vec::new();
+    for command in zkapp_commands {
+      let position = SearchTransactionsCursor::from(&command);
+      match zkapp_command_rows.last_mut() {
+        Some((last, rows)) if *last == position => rows.push(command),
+        _ => zkapp_command_rows.push((position, vec![command])),
+      }
     }
-
-    // ZkApp Commands
-    if limit > transactions.len() as i64 {
-      // if we are below the limit, fetch zkapp commands
-      (offset, limit) = adjust_limit_and_offset(limit, offset, internal_commands_bt_len as i64);
-      tracing::debug!("Offset: {}, Limit: {}", offset, limit);
-      let zkapp_commands = self.fetch_zkapp_commands(&query_params, offset, limit).await?;
-      let zkapp_commands_total_count = zkapp_commands.first().and_then(|ic| ic.total_count).unwrap_or(0);
-      let zkapp_commands_bt = zkapp_commands_to_block_transactions(zkapp_commands, include_timestamp);
-      let zkapp_commands_bt_len = zkapp_commands_bt.len();
-      transactions.extend(zkapp_commands_bt);
-      total_count += zkapp_commands_total_count;
-      tracing::debug!("Zkapp commands total: {}, retrieved: {}", zkapp_commands_total_count, zkapp_commands_bt_len);
-    } else {
-      // otherwise only fetch the first zkapp command to get the total count
-      let zkapp_commands = self.fetch_zkapp_commands(&query_params, 0, 1).await?;
-      let zkapp_commands_total_count = zkapp_commands.first().and_then(|ic| ic.total_count).unwrap_or(0);
-      total_count += zkapp_commands_total_count;
-      tracing::debug!("Zkapp commands total: {}", zkapp_commands_total_count);
+    transactions.extend(zkapp_command_rows.into_iter().flat_map(|(position, rows)| {
+      zkapp_commands_to_block_transactions(rows, include_timestamp)
+        .into_iter()
+        .map(move |transaction| (position, transaction))
+    }));
+
+    match order {
+      SortOrder::Asc => transactions.sort_by_key(|(position, _)| *position),
+      SortOrder::Desc => transactions.sort_by_key(|(position, _)| Reverse(*position)),
     }
+    let transactions = transactions.into_iter().take(limit as usize).collect::<Vec<_>>();
+    let next_cursor = transactions.last().map(|(position, _)| position.encode());
+    tracing::debug!(
+      "Total tx count: {}, retrieved: {}, next_cursor: {:?}",
+      total_count,
+      transactions.len(),
+      next_cursor
+    );
+
+    let response = SearchTransactionsResponse {
+      transactions: transactions.into_iter().map(|(_, transaction)| transaction).collect(),
+      total_count,
+      next_offset: None,
+    };
+    Ok(SearchTransactionsPageResponse { response, next_cursor })
+  }
+
+  /// The results as the user, then internal, then zkApp commands, paged by
+  /// offset. The page spans a window of each kind, found from their total
+  /// counts, and the windows are queried concurrently. The counts are cached
+  /// up to the highest block, so that they are recounted once blocks are
+  /// added rather than misplacing the windows.
+  async fn search_archive_transactions(
+    &self,
+    req: SearchTransactionsRequest,
+    metadata: &SearchTransactionsMetadata,
+  ) -> Result<SearchTransactionsResponse, MinaMeshError> {
+    let offset = req.offset.unwrap_or(0);
+    let limit = req.limit.unwrap_or(100).clamp(0, MAX_SEARCH_TRANSACTIONS_LIMIT);
+    tracing::debug!("{:?} {:?}", req, metadata);
+    tracing::debug!("Offset: {}, Limit: {}", offset, limit);
 
-    let next_offset = original_offset + transactions.len() as i64;
+    let mut query_params = SearchTransactionsQueryParams::try_from(req.clone())?.with_metadata(metadata);
+    let include_timestamp = req.include_timestamp.unwrap_or(false);
+    let (pg_pool, _) = self.search_pg_pool().await?;
+
+    let max_height =
+      sqlx::query_file!("sql/queries/max_height.sql").fetch_one(pg_pool).await?.max_height.unwrap_or_default();
+    query_params.max_block = Some(query_params.max_block.map_or(max_height, |max| max.min(max_height)));
+    let counts = match self.cache.search_counts.get(&query_params.counts_key()) {
+      Some(counts) => counts,
+      None => {
+        let counts = self.count_search(pg_pool, &query_params, None, None, None).await?;
+        self.cache.search_counts.insert(query_params.counts_key(), counts);
+        counts
+      }
+    };
+    let window = |start: i64, count: i64| {
+      let from = (offset - start).clamp(0, count);
+      let to = (offset + limit - start).clamp(0, count);
+      (from, to - from)
+    };
+    let (user_offset, user_limit) = window(0, counts.user);
+    let (internal_offset, internal_limit) = window(counts.user, counts.internal);
+    let (zkapp_offset, zkapp_limit) = window(counts.user + counts.internal, counts.zkapp);
+    tracing::debug!(
+      "User commands: {}/{}, internal commands: {}/{}, zkApp commands: {}/{}",
+      user_offset,
+      user_limit,
+      internal_offset,
+      internal_limit,
+      zkapp_offset,
+      zkapp_limit
+    );
+
+    // Kinds outside of the page are not queried
+    let (user_commands, internal_commands, zkapp_commands) = tokio::try_join!(
+      async {
+        match user_limit {
+          0 => Ok(Vec::new()),
+          _ => self.fetch_user_commands(pg_pool, &query_params, None, false, user_offset, user_limit).await,
+        }
+      },
+      async {
+        match internal_limit {
+          0 => Ok(Vec::new()),
+          _ => self.fetch_internal_commands(pg_pool, &query_params, None, false, internal_offset, internal_limit).await,
+        }
+      },
+      async {
+        match zkapp_limit {
+          0 => Ok(Vec::new()),
+          _ => self.fetch_zkapp_commands(pg_pool, &query_params, None, false, zkapp_offset, zkapp_limit).await,
+        }
+      },
+    )?;
+
+    let mut transactions = map_to_block_transactions(user_commands, include_timestamp);
+    transactions.extend(map_to_block_transactions(internal_commands, include_timestamp));
+    transactions.extend(zkapp_commands_to_block_transactions(zkapp_commands, include_timestamp));
+
+    let total_count = counts.total();
+    let next_offset = offset + transactions.len() as i64;
     let tx_len = transactions.len() as i64;
     let response = SearchTransactionsResponse {
       transactions,
@@ -92,12 +254,121 @@ impl MinaMesh {
     Ok(response)
   }
 
+  /// The total counts of the search by kind of command. Kinds whose count is
+  /// given are not counted again.
+  async fn count_search(
+    &self,
+    pg_pool: &PgPool,
+    query_params: &SearchTransactionsQueryParams,
+    user: Option<i64>,
+    internal: Option<i64>,
+    zkapp: Option<i64>,
+  ) -> Result<SearchCounts, MinaMeshError> {
+    // The count is returned along with the first command
+    let (user, internal, zkapp) = tokio::try_join!(
+      async {
+        match user {
+          Some(count) => Ok(count),
+          None => Ok(
+            self
+              .fetch_user_commands(pg_pool, query_params, None, true, 0, 1)
+              .await?
+              .first()
+              .and_then(|uc| uc.total_count)
+              .unwrap_or(0),
+          ),
+        }
+      },
+      async {
+        match internal {
+          Some(count) => Ok(count),
+          None => Ok(
+            self
+              .fetch_internal_commands(pg_pool, query_params, None, true, 0, 1)
+              .await?
+              .first()
+              .and_then(|ic| ic.total_count)
+              .unwrap_or(0),
+          ),
+        }
+      },
+      async {
+        match zkapp {
+          Some(count) => Ok(count),
+          None => Ok(
+            self
+              .fetch_zkapp_commands(pg_pool, query_params, None, true, 0, 1)
+              .await?
+              .first()
+              .and_then(|zc| zc.total_count)
+              .unwrap_or(0),
+          ),
+        }
+      },
+    )?;
+    let counts = SearchCounts { user, internal, zkapp };
+    tracing::debug!("Counts: {:?}", counts);
+    Ok(counts)
+  }
+
+  /// The read replica if one is configured and keeps up with the Archive
+  /// Database, the latter otherwise, along with the highest canonical block it
+  /// holds. Both are checked at most once per [SEARCH_POOL_CHECK_INTERVAL].
+  async fn search_pg_pool(&self) -> Result<(&PgPool, i64), MinaMeshError> {
+    let last_check = *self.search_pool.lock().expect("Search pool lock poisoned");
+    let search_pool = match last_check {
+      Some(search_pool) if search_pool.checked_at.elapsed() < SEARCH_POOL_CHECK_INTERVAL => search_pool,
+      _ => {
+        let search_pool = self.check_search_pool().await?;
+        *self.search_pool.lock().expect("Search pool lock poisoned") = Some(search_pool);
+        search_pool
+      }
+    };
+    let pg_pool = match &self.replica_pg_pool {
+      Some(replica_pg_pool) if search_pool.replica => replica_pg_pool,
+      _ => &self.pg_pool,
+    };
+    Ok((pg_pool, search_pool.max_canonical_height))
+  }
+
+  async fn check_search_pool(&self) -> Result<SearchPool, MinaMeshError> {
+    let checked_at = Instant::now();
+    let max_canonical_height = |pg_pool| async move {
+      sqlx::query_file!("sql/queries/max_canonical_height.sql")
+        .fetch_one(pg_pool)
+        .await
+        .map(|record| record.max_canonical_height.unwrap_or_default())
+    };
+    let Some(replica_pg_pool) = &self.replica_pg_pool else {
+      let max_canonical_height = max_canonical_height(&self.pg_pool).await?;
+      return Ok(SearchPool { replica: false, max_canonical_height, checked_at });
+    };
+    let (primary, replica) = tokio::join!(max_canonical_height(&self.pg_pool), max_canonical_height(replica_pg_pool));
+    let primary = primary?;
+    match replica {
+      Ok(replica) => {
+        let lag = primary - replica;
+        if lag <= self.max_replica_lag as i64 {
+          return Ok(SearchPool { replica: true, max_canonical_height: replica, checked_at });
+        }
+        tracing::warn!("Read replica lags {} canonical blocks behind, querying the Archive Database", lag);
+      }
+      Err(e) => tracing::warn!("Read replica unavailable, querying the Archive Database: {}", e),
+    }
+    Ok(SearchPool { replica: false, max_canonical_height: primary, checked_at })
+  }
+
   pub async fn fetch_user_commands(
     &self,
+    pg_pool: &PgPool,
     query_params: &SearchTransactionsQueryParams,
+    cursor: Option<&SearchTransactionsCursor>,
+    count: bool,
     offset: i64,
     limit: i64,
   ) -> Result<Vec<UserCommand>, MinaMeshError> {
+    let mut transaction = begin_archive_transaction(pg_pool).await?;
+    let bound = cursor.map(|cursor| cursor.bound(CommandKind::User));
     if !self.search_tx_optimized {
       let user_commands = sqlx::query_file_as!(
         UserCommand,
@@ -111,8 +382,21 @@ impl MinaMesh {
         query_params.address,
         limit,
         offset,
+        bound.map(|bound| bound.height),
+        bound.map(|bound| bound.block_id),
+        bound.map(|bound| bound.sequence_no),
+        bound.map(|bound| bound.id),
+        query_params.any_condition,
+        query_params.user_command_types.as_deref(),
+        query_params.account_creation_fee,
+        query_params.currency_token_id.as_deref().map(is_default_token),
+        query_params.min_block,
+        query_params.min_timestamp,
+        query_params.max_timestamp,
+        query_params.descending,
+        count,
       )
-      .fetch_all(&self.pg_pool)
+      .fetch_all(&mut *transaction)
       .await?;
       Ok(user_commands)
     } else {
@@ -128,8 +412,21 @@ impl MinaMesh {
         query_params.address,
         limit,
         offset,
+        bound.map(|bound| bound.height),
+        bound.map(|bound| bound.block_id),
+        bound.map(|bound| bound.sequence_no),
+        bound.map(|bound| bound.id),
+        query_params.any_condition,
+        query_params.user_command_types.as_deref(),
+        query_params.account_creation_fee,
+        query_params.currency_token_id.as_deref().map(is_default_token),
+        query_params.min_block,
+        query_params.min_timestamp,
+        query_params.max_timestamp,
+        query_params.descending,
+        count,
       )
-      .fetch_all(&self.pg_pool)
+      .fetch_all(&mut *transaction)
       .await?;
       Ok(user_commands)
     }
@@ -137,10 +434,15 @@ impl MinaMesh {
 
   pub async fn fetch_internal_commands(
     &self,
+    pg_pool: &PgPool,
     query_params: &SearchTransactionsQueryParams,
+    cursor: Option<&SearchTransactionsCursor>,
+    count: bool,
     offset: i64,
     limit: i64,
   ) -> Result<Vec<InternalCommand>, MinaMeshError> {
+    let mut transaction = begin_archive_transaction(pg_pool).await?;
+    let bound = cursor.map(|cursor| cursor.bound(CommandKind::Internal));
     if !self.search_tx_optimized {
       let internal_commands = sqlx::query_file_as!(
         InternalCommand,
@@ -153,9 +455,23 @@ impl MinaMesh {
         query_params.success_status.clone() as Option<TransactionStatus>,
         query_params.address,
         limit,
-        offset
+        offset,
+        bound.map(|bound| bound.height),
+        bound.map(|bound| bound.block_id),
+        bound.map(|bound| bound.sequence_no),
+        bound.map(|bound| bound.secondary_sequence_no),
+        bound.map(|bound| bound.id),
+        query_params.any_condition,
+        query_params.internal_command_types.as_deref(),
+        query_params.account_creation_fee,
+        query_params.currency_token_id.as_deref().map(is_default_token),
+        query_params.min_block,
+        query_params.min_timestamp,
+        query_params.max_timestamp,
+        query_params.descending,
+        count,
       )
-      .fetch_all(&self.pg_pool)
+      .fetch_all(&mut *transaction)
       .await?;
 
       Ok(internal_commands)
@@ -171,9 +487,23 @@ impl MinaMesh {
         query_params.success_status.clone() as Option<TransactionStatus>,
         query_params.address,
         limit,
-        offset
+        offset,
+        bound.map(|bound| bound.height),
+        bound.map(|bound| bound.block_id),
+        bound.map(|bound| bound.sequence_no),
+        bound.map(|bound| bound.secondary_sequence_no),
+        bound.map(|bound| bound.id),
+        query_params.any_condition,
+        query_params.internal_command_types.as_deref(),
+        query_params.account_creation_fee,
+        query_params.currency_token_id.as_deref().map(is_default_token),
+        query_params.min_block,
+        query_params.min_timestamp,
+        query_params.max_timestamp,
+        query_params.descending,
+        count,
       )
-      .fetch_all(&self.pg_pool)
+      .fetch_all(&mut *transaction)
       .await?;
 
       Ok(internal_commands)
@@ -182,10 +512,15 @@ impl MinaMesh {
 
   async fn fetch_zkapp_commands(
     &self,
+    pg_pool: &PgPool,
     query_params: &SearchTransactionsQueryParams,
+    cursor: Option<&SearchTransactionsCursor>,
+    count: bool,
     offset: i64,
     limit: i64,
   ) -> Result<Vec<ZkAppCommand>, MinaMeshError> {
+    let mut transaction = begin_archive_transaction(pg_pool).await?;
+    let bound = cursor.map(|cursor| cursor.bound(CommandKind::ZkApp));
     if !self.search_tx_optimized {
       let zkapp_commands = sqlx::query_file_as!(
         ZkAppCommand,
@@ -198,9 +533,21 @@ impl MinaMesh {
         query_params.success_status.clone() as Option<TransactionStatus>,
         query_params.address,
         limit,
-        offset
+        offset,
+        bound.map(|bound| bound.height),
+        bound.map(|bound| bound.block_id),
+        bound.map(|bound| bound.sequence_no),
+        bound.map(|bound| bound.id),
+        query_params.any_condition,
+        query_params.zkapp_type,
+        query_params.currency_token_id,
+        query_params.min_block,
+        query_params.min_timestamp,
+        query_params.max_timestamp,
+        query_params.descending,
+        count,
       )
-      .fetch_all(&self.pg_pool)
+      .fetch_all(&mut *transaction)
       .await?;
 
       Ok(zkapp_commands)
@@ -216,9 +563,21 @@ impl MinaMesh {
         query_params.success_status.clone() as Option<TransactionStatus>,
         query_params.address,
         limit,
-        offset
+        offset,
+        bound.map(|bound| bound.height),
+        bound.map(|bound| bound.block_id),
+        bound.map(|bound| bound.sequence_no),
+        bound.map(|bound| bound.id),
+        query_params.any_condition,
+        query_params.zkapp_type,
+        query_params.currency_token_id,
+        query_params.min_block,
+        query_params.min_timestamp,
+        query_params.max_timestamp,
+        query_params.descending,
+        count,
       )
-      .fetch_all(&self.pg_pool)
+      .fetch_all(&mut *transaction)
       .await?;
 
       Ok(zkapp_commands)
@@ -265,19 +624,21 @@ fn map_to_block_transactions<T>(commands: Vec<T>, include_timestamp: bool) -> Ve
 where
   T: Into<BlockTransaction> + HasTimestamp,
 {
-  commands
-    .into_iter()
-    .map(|cmd| {
-      let timestamp = cmd.timestamp().map(|ts| ts.parse::<i64>().unwrap_or_default());
-      let mut transaction: BlockTransaction = cmd.into();
-      if include_timestamp {
-        transaction.timestamp = timestamp;
-      } else {
-        transaction.timestamp = None;
-      }
-      transaction
-    })
-    .collect()
+  commands.into_iter().map(|cmd| to_block_transaction(cmd, include_timestamp)).collect()
+}
+
+fn to_block_transaction<T>(cmd: T, include_timestamp: bool) -> BlockTransaction
+where
+  T: Into<BlockTransaction> + HasTimestamp,
+{
+  let timestamp = cmd.timestamp().map(|ts| ts.parse::<i64>().unwrap_or_default());
+  let mut transaction: BlockTransaction = cmd.into();
+  if include_timestamp {
+    transaction.timestamp = timestamp;
+  } else {
+    transaction.timestamp = None;
+  }
+  transaction
 }
 
 impl From<InternalCommand> for BlockTransaction {
@@ -325,6 +686,7 @@ impl From<UserCommand> for BlockTransaction {
   }
 }
 
+#[derive(Clone)]
 pub struct SearchTransactionsQueryParams {
   pub max_block: Option<i64>,
   pub transaction_hash: Option<String>,
@@ -333,14 +695,58 @@ pub struct SearchTransactionsQueryParams {
   pub status: Option<TransactionStatus>,
   pub success_status: Option<TransactionStatus>,
   pub address: Option<String>,
+  pub any_condition: bool,
+  pub user_command_types: Option<Vec<String>>,
+  pub internal_command_types: Option<Vec<String>>,
+  pub zkapp_type: Option<bool>,
+  pub account_creation_fee: bool,
+  pub currency_token_id: Option<String>,
+  pub min_block: Option<i64>,
+  pub min_timestamp: Option<i64>,
+  pub max_timestamp: Option<i64>,
+  /// The direction of the global order, which only applies if sorted or paged
+  /// by cursor
+  pub descending: Option<bool>,
+}
+
+impl SearchTransactionsQueryParams {
+  /// The filters of the search, regardless of its order.
+  fn counts_key(&self) -> String {
+    Self { descending: None, ..self.clone() }.to_string()
+  }
+
+  fn with_metadata(self, metadata: &SearchTransactionsMetadata) -> Self {
+    Self {
+      min_block: metadata.min_block,
+      min_timestamp: metadata.min_timestamp,
+      max_timestamp: metadata.max_timestamp,
+      ..self
+    }
+  }
 }
 
 impl std::fmt::Display for SearchTransactionsQueryParams {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(
       f,
-      "max_block: {:?}, transaction_hash: {:?}, account_identifier: {:?}, token_id: {:?}, status: {:?}, success_status: {:?}, address: {:?}",
-      self.max_block, self.transaction_hash, self.account_identifier, self.token_id, self.status, self.success_status, self.address
+      "max_block: {:?}, transaction_hash: {:?}, account_identifier: {:?}, token_id: {:?}, status: {:?}, success_status: {:?}, address: {:?}, any_condition: {:?}, user_command_types: {:?}, internal_command_types: {:?}, zkapp_type: {:?}, account_creation_fee: {:?}, currency_token_id: {:?}, min_block: {:?}, min_timestamp: {:?}, max_timestamp: {:?}, descending: {:?}",
+      self.max_block,
+      self.transaction_hash,
+      self.account_identifier,
+      self.token_id,
+      self.status,
+      self.success_status,
+      self.address,
+      self.any_condition,
+      self.user_command_types,
+      self.internal_command_types,
+      self.zkapp_type,
+      self.account_creation_fee,
+      self.currency_token_id,
+      self.min_block,
+      self.min_timestamp,
+      self.max_timestamp,
+      self.descending
     )
   }
 }
@@ -357,7 +763,13 @@ impl TryFrom<SearchTransactionsRequest> for SearchTransactionsQueryParams {
       .as_ref()
       .and_then(|a| a.metadata.as_ref())
       .and_then(|m| m.get("token_id"))
-      .map(|t| t.as_str().unwrap().to_string());
+      .map(|token_id| {
+        token_id
+          .as_str()
+          .map(normalize_token_id)
+          .ok_or_else(|| MinaMeshError::JsonParse(Some(format!("Invalid account_identifier token_id: {}", token_id))))
+      })
+      .transpose()?;
     let account_identifier = req.account_identifier.map(|a| a.address);
 
     let status = match req.status.as_deref() {
@@ -378,6 +790,25 @@ impl TryFrom<SearchTransactionsRequest> for SearchTransactionsQueryParams {
       None => None,
     };
 
+    if req.coin_identifier.is_some() {
+      return Err(MinaMeshError::Exception(
+        "Searching by coin_identifier is not supported, as Mina is account-based".to_string(),
+      ));
+    }
+
+    let any_condition = matches!(req.operator, Some(Operator::Or));
+    let operation_type = match req.r#type.as_deref() {
+      Some(r#type) => Some(OperationType::from_str(r#type).map_err(|_| {
+        MinaMeshError::Exception(format!(
+          "Invalid operation type: '{}'. Valid types are listed by /network/options",
+          r#type
+        ))
+      })?),
+      None => None,
+    };
+    let types = operation_type.as_ref().map(command_types);
+    let currency_token_id = req.currency.as_deref().map(currency_to_token_id).transpose()?;
+
     let address = req.address;
     let st = SearchTransactionsQueryParams {
       max_block,
@@ -387,21 +818,68 @@ impl TryFrom<SearchTransactionsRequest> for SearchTransactionsQueryParams {
       status,
       success_status,
       address,
+      any_condition,
+      user_command_types: types.as_ref().map(|types| types.user.iter().map(|t| t.to_string()).collect()),
+      internal_command_types: types.as_ref().map(|types| types.internal.iter().map(|t| t.to_string()).collect()),
+      zkapp_type: types.as_ref().map(|types| types.zkapp),
+      account_creation_fee: types.as_ref().is_some_and(|types| types.account_creation_fee),
+      currency_token_id,
+      min_block: None,
+      min_timestamp: None,
+      max_timestamp: None,
+      descending: None,
     };
     Ok(st)
   }
 }
 
-fn adjust_limit_and_offset(mut limit: i64, mut offset: i64, txs_len: i64) -> (i64, i64) {
-  if offset >= txs_len {
-    offset -= txs_len;
-  } else {
-    offset = 0;
+/// The commands which have operations of a given type.
+struct CommandTypes {
+  user: &'static [&'static str],
+  internal: &'static [&'static str],
+  zkapp: bool,
+  /// Only the commands which pay an account creation fee
+  account_creation_fee: bool,
+}
+
+fn command_types(operation_type: &OperationType) -> CommandTypes {
+  let (user, internal, zkapp, account_creation_fee): (&[&str], &[&str], _, _) = match operation_type {
+    OperationType::FeePayment => (&["payment", "delegation"], &[], false, false),
+    OperationType::PaymentSourceDec | OperationType::PaymentReceiverInc => (&["payment"], &[], false, false),
+    OperationType::DelegateChange => (&["delegation"], &[], false, false),
+    OperationType::AccountCreationFeeViaPayment => (&["payment", "delegation"], &[], false, true),
+    OperationType::CoinbaseInc => (&[], &["coinbase"], false, false),
+    OperationType::FeeReceiverInc => (&[], &["fee_transfer", "fee_transfer_via_coinbase"], false, false),
+    OperationType::FeePayerDec => (&[], &["fee_transfer_via_coinbase"], false, false),
+    OperationType::AccountCreationFeeViaFeeReceiver => {
+      (&[], &["coinbase", "fee_transfer", "fee_transfer_via_coinbase"], false, true)
+    }
+    OperationType::ZkappFeePayerDec | OperationType::ZkappBalanceUpdate => (&[], &[], true, false),
+  };
+  CommandTypes { user, internal, zkapp, account_creation_fee }
+}
+
+// The currency is identified by its token ID in the metadata, as returned in
+// operations, or is MINA itself.
+fn currency_to_token_id(currency: &Currency) -> Result<String, MinaMeshError> {
+  if let Some(token_id) = currency.metadata.as_ref().and_then(|metadata| metadata.get("token_id")) {
+    return token_id
+      .as_str()
+      .map(normalize_token_id)
+      .ok_or_else(|| MinaMeshError::Exception(format!("Invalid currency token_id: {}", token_id)));
+  }
+  match currency.symbol.as_str() {
+    "MINA" => Ok(DEFAULT_TOKEN_ID.to_string()),
+    symbol => Err(MinaMeshError::Exception(format!("Unsupported currency: '{}', expected a token_id", symbol))),
   }
-  if limit >= txs_len {
-    limit -= txs_len;
+}
+
+// Token IDs are compared as the Archive Database stores them, so the legacy ID
+// of MINA is replaced by the current one.
+fn normalize_token_id(token_id: &str) -> String {
+  if is_default_token(token_id) {
+    DEFAULT_TOKEN_ID.to_string()
   } else {
-    limit = 0;
+    token_id.to_string()
   }
-  (offset, limit)
 }
@@ -2,8 +2,8 @@
 // hash.
 
 use anyhow::Result;
-use clap::Parser;
-use mina_mesh::{SearchTxOptimizationsCommand, ServeCommand};
+use clap::{CommandFactory, FromArgMatches, Parser};
+use mina_mesh::{config_file_path, load_config_file, ConfigCommand, SearchTxOptimizationsCommand, ServeCommand};
 use tokio::{select, signal};
 
 #[derive(Debug, Parser)]
@@ -11,15 +11,24 @@ use tokio::{select, signal};
 enum Command {
   Serve(ServeCommand),
   SearchTxOptimizations(SearchTxOptimizationsCommand),
+  Config(ConfigCommand),
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+// The environment is set up before the runtime starts its threads.
+fn main() -> Result<()> {
   dotenv::dotenv().ok();
-  match Command::parse() {
-    Command::Serve(cmd) => cmd.run(shutdown_signal()).await,
-    Command::SearchTxOptimizations(cmd) => cmd.run().await,
+  let mut command = Command::command();
+  if let Some(path) = config_file_path() {
+    command = load_config_file(&path)?.apply(command);
   }
+  let command = Command::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit());
+  tokio::runtime::Builder::new_multi_thread().enable_all().build()?.block_on(async {
+    match command {
+      Command::Serve(cmd) => cmd.run(shutdown_signal()).await,
+      Command::SearchTxOptimizations(cmd) => cmd.run().await,
+      Command::Config(cmd) => cmd.run(),
+    }
+  })
 }
 
 pub async fn shutdown_signal() {
@@ -0,0 +1,252 @@
+use std::{
+  fmt::Debug,
+  hash::Hash,
+  num::NonZeroUsize,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+use coinbase_mesh::models::BlockResponse;
+use serde::Serialize;
+
+use crate::{metrics, CacheKey, SearchCounts};
+
+/// A bounded, thread-safe cache. Implementations decide on eviction and
+/// expiry, and keep track of their hit rate.
+pub trait Cache<K, V>: Debug + Send + Sync {
+  fn get(&self, key: &K) -> Option<V>;
+  fn insert(&self, key: K, value: V);
+  fn remove(&self, key: &K);
+  fn stats(&self) -> CacheStats;
+}
+
+/// The `capacity` is in entries, or in bytes for caches bounded by size, which
+/// also report the size of their entries in `bytes`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+  pub hits: u64,
+  pub misses: u64,
+  pub entries: usize,
+  pub capacity: usize,
+  pub bytes: Option<usize>,
+}
+
+/// Capacity and time-to-live of a cache. A `ttl` of `None` keeps entries until
+/// they are evicted, a `capacity` of 0 disables the cache.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheConfig {
+  pub capacity: usize,
+  pub ttl: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub(crate) struct LookupCounters {
+  name: &'static str,
+  hits: AtomicU64,
+  misses: AtomicU64,
+}
+
+impl LookupCounters {
+  pub(crate) fn new(name: &'static str) -> Self {
+    Self { name, hits: AtomicU64::new(0), misses: AtomicU64::new(0) }
+  }
+
+  pub(crate) fn record(&self, hit: bool) {
+    let counter = if hit { &self.hits } else { &self.misses };
+    counter.fetch_add(1, Ordering::Relaxed);
+    metrics::record_cache_lookup(self.name, hit);
+  }
+
+  pub(crate) fn hits(&self) -> u64 {
+    self.hits.load(Ordering::Relaxed)
+  }
+
+  pub(crate) fn misses(&self) -> u64 {
+    self.misses.load(Ordering::Relaxed)
+  }
+}
+
+/// Evicts the least recently used entry once full, and drops entries older
+/// than the time-to-live on lookup.
+#[derive(Debug)]
+pub struct LruTtlCache<K: Hash + Eq, V> {
+  ttl: Option<Duration>,
+  capacity: usize,
+  entries: Option<Mutex<lru::LruCache<K, (V, Instant)>>>,
+  lookups: LookupCounters,
+}
+
+impl<K: Hash + Eq, V> LruTtlCache<K, V> {
+  pub fn new(name: &'static str, config: CacheConfig) -> Self {
+    Self {
+      ttl: config.ttl,
+      capacity: config.capacity,
+      entries: NonZeroUsize::new(config.capacity).map(|capacity| Mutex::new(lru::LruCache::new(capacity))),
+      lookups: LookupCounters::new(name),
+    }
+  }
+}
+
+impl<K, V> Cache<K, V> for LruTtlCache<K, V>
+where
+  K: Hash + Eq + Debug + Send,
+  V: Clone + Debug + Send,
+{
+  fn get(&self, key: &K) -> Option<V> {
+    let value = self.entries.as_ref().and_then(|entries| {
+      let mut entries = entries.lock().expect("Cache lock poisoned");
+      match entries.get(key) {
+        Some((_, inserted_at)) if self.ttl.is_some_and(|ttl| inserted_at.elapsed() >= ttl) => {
+          entries.pop(key);
+          None
+        }
+        Some((value, _)) => Some(value.clone()),
+        None => None,
+      }
+    });
+    self.lookups.record(value.is_some());
+    value
+  }
+
+  fn insert(&self, key: K, value: V) {
+    if let Some(entries) = &self.entries {
+      entries.lock().expect("Cache lock poisoned").put(key, (value, Instant::now()));
+    }
+  }
+
+  fn remove(&self, key: &K) {
+    if let Some(entries) = &self.entries {
+      entries.lock().expect("Cache lock poisoned").pop(key);
+    }
+  }
+
+  fn stats(&self) -> CacheStats {
+    CacheStats {
+      hits: self.lookups.hits(),
+      misses: self.lookups.misses(),
+      entries: self.entries.as_ref().map_or(0, |entries| entries.lock().expect("Cache lock poisoned").len()),
+      capacity: self.capacity,
+      bytes: None,
+    }
+  }
+}
+
+#[derive(Debug)]
+struct WeighedEntries<K: Hash + Eq, V> {
+  entries: lru::LruCache<K, (V, usize)>,
+  bytes: usize,
+}
+
+/// Evicts the least recently used entries once their total size exceeds the
+/// capacity in bytes. Entries never expire, so this suits immutable values.
+#[derive(Debug)]
+pub struct SizeBoundedLruCache<K: Hash + Eq, V> {
+  capacity: usize,
+  weigh: fn(&V) -> usize,
+  entries: Mutex<WeighedEntries<K, V>>,
+  lookups: LookupCounters,
+}
+
+impl<K: Hash + Eq, V> SizeBoundedLruCache<K, V> {
+  pub fn new(name: &'static str, capacity: usize, weigh: fn(&V) -> usize) -> Self {
+    Self {
+      capacity,
+      weigh,
+      entries: Mutex::new(WeighedEntries { entries: lru::LruCache::unbounded(), bytes: 0 }),
+      lookups: LookupCounters::new(name),
+    }
+  }
+}
+
+impl<K, V> Cache<K, V> for SizeBoundedLruCache<K, V>
+where
+  K: Hash + Eq + Debug + Send,
+  V: Clone + Debug + Send,
+{
+  fn get(&self, key: &K) -> Option<V> {
+    let value = self.entries.lock().expect("Cache lock poisoned").entries.get(key).map(|(value, _)| value.clone());
+    self.lookups.record(value.is_some());
+    value
+  }
+
+  fn insert(&self, key: K, value: V) {
+    let weight = (self.weigh)(&value);
+    // Also covers a capacity of 0, which disables the cache
+    if weight > self.capacity {
+      return;
+    }
+    let mut entries = self.entries.lock().expect("Cache lock poisoned");
+    if let Some((_, replaced_weight)) = entries.entries.put(key, (value, weight)) {
+      entries.bytes -= replaced_weight;
+    }
+    entries.bytes += weight;
+    while entries.bytes > self.capacity {
+      match entries.entries.pop_lru() {
+        Some((_, (_, evicted_weight))) => entries.bytes -= evicted_weight,
+        None => break,
+      }
+    }
+  }
+
+  fn remove(&self, key: &K) {
+    let mut entries = self.entries.lock().expect("Cache lock poisoned");
+    if let Some((_, weight)) = entries.entries.pop(key) {
+      entries.bytes -= weight;
+    }
+  }
+
+  fn stats(&self) -> CacheStats {
+    let entries = self.entries.lock().expect("Cache lock poisoned");
+    CacheStats {
+      hits: self.lookups.hits(),
+      misses: self.lookups.misses(),
+      entries: entries.entries.len(),
+      capacity: self.capacity,
+      bytes: Some(entries.bytes),
+    }
+  }
+}
+
+/// The size of a `/block` response as served, measured once on insert. A
+/// response which cannot be serialized is never cached.
+fn block_size(response: &BlockResponse) -> usize {
+  serde_json::to_vec(response).map_or(usize::MAX, |bytes| bytes.len())
+}
+
+/// The caches of [crate::MinaMesh]: one per [CacheKey] kind, one of `/block`
+/// responses of canonical blocks by state hash, and one of the total counts by
+/// kind of command of `/search/transactions` searches by their filters. Submitted transactions
+/// are remembered by the [crate::SubmissionStore] instead.
+#[derive(Debug)]
+pub struct Caches {
+  pub network_id: Box<dyn Cache<CacheKey, String>>,
+  pub blocks: Box<dyn Cache<String, BlockResponse>>,
+  pub search_counts: Box<dyn Cache<String, SearchCounts>>,
+}
+
+impl Caches {
+  pub fn new(network_id: CacheConfig, block_bytes: usize, search_counts: CacheConfig) -> Self {
+    Self {
+      network_id: Box::new(LruTtlCache::new("network_id", network_id)),
+      blocks: Box::new(SizeBoundedLruCache::new("block", block_bytes, block_size)),
+      search_counts: Box::new(LruTtlCache::new("search_count", search_counts)),
+    }
+  }
+
+  pub fn for_key(&self, key: &CacheKey) -> &dyn Cache<CacheKey, String> {
+    match key {
+      CacheKey::NetworkId => self.network_id.as_ref(),
+    }
+  }
+
+  pub fn stats(&self) -> [(&'static str, CacheStats); 3] {
+    [
+      ("network_id", self.network_id.stats()),
+      ("block", self.blocks.stats()),
+      ("search_count", self.search_counts.stats()),
+    ]
+  }
+}
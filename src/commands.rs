@@ -1,5 +1,7 @@
+mod config;
 mod search_tx_optimizations;
 mod serve;
 
+pub use config::*;
 pub use search_tx_optimizations::*;
 pub use serve::*;
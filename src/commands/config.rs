@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+
+use crate::MinaMeshConfig;
+
+#[derive(Debug, Args)]
+#[command(about = "Command to inspect the configuration of the Mina Mesh Server.")]
+pub struct ConfigCommand {
+  #[command(subcommand)]
+  action: ConfigAction,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigAction {
+  /// Print the effective configuration as TOML, merged from the configuration
+  /// file, environment variables and flags, with credentials redacted.
+  Print {
+    #[command(flatten)]
+    config: MinaMeshConfig,
+    /// A TOML or YAML configuration file. Flags take precedence over
+    /// environment variables, which take precedence over the file.
+    #[arg(long = "config", env = "MINAMESH_CONFIG")]
+    config_file: Option<PathBuf>,
+  },
+}
+
+impl ConfigCommand {
+  pub fn run(&self) -> Result<()> {
+    match &self.action {
+      ConfigAction::Print { config, config_file } => {
+        if let Some(config_file) = config_file {
+          println!("# Configuration file: {}", config_file.display());
+        }
+        print!("{}", config.to_redacted_toml()?);
+      }
+    }
+    Ok(())
+  }
+}
@@ -1,4 +1,4 @@
-use std::future::Future;
+use std::{future::Future, path::PathBuf};
 
 use anyhow::Result;
 use axum::serve;
@@ -12,6 +12,10 @@ use crate::{create_router, MinaMeshConfig};
 pub struct ServeCommand {
   #[command(flatten)]
   config: MinaMeshConfig,
+  /// A TOML or YAML configuration file. Flags take precedence over environment
+  /// variables, which take precedence over the file.
+  #[arg(long = "config", env = "MINAMESH_CONFIG")]
+  config_file: Option<PathBuf>,
   #[arg(default_value = "0.0.0.0")]
   host: String,
   #[arg(default_value = "3000")]
@@ -27,6 +31,9 @@ impl ServeCommand {
     F: Future<Output = ()> + Send + 'static,
   {
     tracing_subscriber::fmt::init();
+    if let Some(config_file) = &self.config_file {
+      tracing::info!("Loaded configuration file {}", config_file.display());
+    }
     let mina_mesh = self.config.to_mina_mesh().await?;
     let router = create_router(mina_mesh, self.playground);
     let listener = TcpListener::bind(format!("{}:{}", self.host, self.port)).await?;
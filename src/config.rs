@@ -1,27 +1,116 @@
-use std::time::Duration;
+use std::{
+  path::PathBuf,
+  str::FromStr,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
 
 use anyhow::Result;
 use clap::{Args, Parser};
 use coinbase_mesh::models::BlockIdentifier;
 use cynic::QueryBuilder;
-use dashmap::DashMap;
-use sqlx::postgres::PgPoolOptions;
+use serde::Serialize;
+use sqlx::{
+  postgres::{PgConnectOptions, PgPoolOptions},
+  PgPool,
+};
 
 use crate::{
-  graphql::{self, GraphQLClient},
+  graphql::{self, GraphQLClient, GraphQLClientPolicy},
   util::default_mina_proxy_url,
-  MinaMesh, MinaMeshError,
+  CacheConfig, Caches, FileSubmissionStore, MemorySubmissionStore, MinaMesh, MinaMeshError, SubmissionStore,
 };
 
-#[derive(Debug, Args)]
+/// The number of searches whose total counts are cached.
+const SEARCH_COUNT_CACHE_SIZE: usize = 1024;
+
+/// Per-endpoint timeouts of the Archive Database queries. `None` disables the
+/// timeout.
+#[derive(Debug, Clone, Default)]
+pub struct StatementTimeouts {
+  pub block: Option<Duration>,
+  pub account_balance: Option<Duration>,
+  pub search_transactions: Option<Duration>,
+}
+
+#[derive(Debug, Args, Serialize)]
 pub struct MinaMeshConfig {
-  /// The URL of the Mina GraphQL
-  #[arg(long, env = "MINAMESH_PROXY_URL", default_value_t = default_mina_proxy_url())]
-  pub proxy_url: String,
+  /// The URLs of the Mina GraphQL, separated by commas. Queries go to the best
+  /// synced one, transaction submissions are broadcast to several of them.
+  #[arg(
+    long = "proxy-url",
+    env = "MINAMESH_PROXY_URL",
+    value_delimiter = ',',
+    default_values_t = [default_mina_proxy_url()]
+  )]
+  pub proxy_urls: Vec<String>,
 
   /// The URL of the Archive Database
-  #[arg(long, env = "MINAMESH_ARCHIVE_DATABASE_URL")]
-  pub archive_database_url: String,
+  #[arg(long, env = "MINAMESH_ARCHIVE_DATABASE_URL", required_unless_present = "offline")]
+  pub archive_database_url: Option<String>,
+
+  /// The URL of a read replica of the Archive Database, serving the expensive
+  /// `/search/transactions` queries.
+  #[arg(long, env = "MINAMESH_ARCHIVE_REPLICA_DATABASE_URL")]
+  pub archive_replica_database_url: Option<String>,
+
+  /// The number of canonical blocks the read replica may lag behind the
+  /// Archive Database before queries fall back to the latter.
+  #[arg(long, env = "MINAMESH_MAX_REPLICA_LAG", default_value_t = 0)]
+  pub max_replica_lag: u32,
+
+  /// The duration (in milliseconds) after which the Archive Database queries
+  /// of `/block` are cancelled. Set to 0 to disable the timeout.
+  #[arg(long, env = "MINAMESH_BLOCK_STATEMENT_TIMEOUT_MS", default_value_t = 10_000)]
+  pub block_statement_timeout_ms: u64,
+
+  /// The duration (in milliseconds) after which the Archive Database queries
+  /// of `/account/balance` are cancelled. Set to 0 to disable the timeout.
+  #[arg(long, env = "MINAMESH_ACCOUNT_BALANCE_STATEMENT_TIMEOUT_MS", default_value_t = 10_000)]
+  pub account_balance_statement_timeout_ms: u64,
+
+  /// The duration (in milliseconds) after which the Archive Database queries
+  /// of `/search/transactions` are cancelled. Set to 0 to disable the timeout.
+  #[arg(long, env = "MINAMESH_SEARCH_TRANSACTIONS_STATEMENT_TIMEOUT_MS", default_value_t = 30_000)]
+  pub search_transactions_statement_timeout_ms: u64,
+
+  /// The number of network ids of the Mina daemon cached at once. Set to 0 to
+  /// disable the cache.
+  #[arg(long, env = "MINAMESH_NETWORK_ID_CACHE_SIZE", default_value_t = 1)]
+  pub network_id_cache_size: usize,
+
+  /// The duration (in seconds) the network id of the Mina daemon is cached
+  /// for. Set to 0 to cache it until restart.
+  #[arg(long, env = "MINAMESH_NETWORK_ID_CACHE_TTL", default_value_t = 300)]
+  pub network_id_cache_ttl: u64,
+
+  /// The number of submitted transactions remembered in memory to detect
+  /// duplicate submissions, unless `--submission-store-path` is set. Set to 0
+  /// to disable the cache.
+  #[arg(long, env = "MINAMESH_TRANSACTION_CACHE_SIZE", default_value_t = 100)]
+  pub transaction_cache_size: usize,
+
+  /// The duration (in seconds) submitted transactions are remembered for. Set
+  /// to 0 to remember them until evicted.
+  #[arg(long, env = "MINAMESH_TRANSACTION_CACHE_TTL", default_value_t = 0)]
+  pub transaction_cache_ttl: u64,
+
+  /// The file submitted transactions are recorded in, so that duplicate
+  /// submissions are detected across restarts and by every server sharing the
+  /// file. By default they are only remembered in memory.
+  #[arg(long, env = "MINAMESH_SUBMISSION_STORE_PATH")]
+  pub submission_store_path: Option<PathBuf>,
+
+  /// The size (in bytes) of the cache of `/block` responses for canonical
+  /// blocks, as serialized. Set to 0 to disable the cache.
+  #[arg(long, env = "MINAMESH_BLOCK_CACHE_SIZE", default_value_t = 64 * 1024 * 1024)]
+  pub block_cache_size: usize,
+
+  /// The duration (in seconds) the total counts of a `/search/transactions`
+  /// search are cached for, so that walking its results by cursor or offset
+  /// does not recount them. Set to 0 to disable the cache.
+  #[arg(long, env = "MINAMESH_SEARCH_COUNT_CACHE_TTL", default_value_t = 30)]
+  pub search_count_cache_ttl: u64,
 
   /// The maximum number of concurrent connections allowed in the Archive
   /// Database connection pool.
@@ -38,6 +127,118 @@ pub struct MinaMeshConfig {
   /// command.
   #[arg(long, env = "USE_SEARCH_TX_OPTIMIZATIONS", default_value = "false")]
   pub use_search_tx_optimizations: bool,
+
+  /// The maximum number of transactions inlined in a `/block` response. Larger
+  /// blocks only list their transaction identifiers in `other_transactions`,
+  /// which can then be fetched via `/block/transaction`.
+  #[arg(long, env = "MINAMESH_MAX_BLOCK_TRANSACTIONS")]
+  pub max_block_transactions: Option<usize>,
+
+  /// Serve only the endpoints which need neither the Mina daemon nor the
+  /// Archive Database: the offline construction endpoints, `/network/list` and
+  /// `/network/options`. Requires `--network-id`.
+  #[arg(long, env = "MINAMESH_OFFLINE", default_value = "false", requires = "network_id")]
+  pub offline: bool,
+
+  /// Serve the Data API from the Archive Database alone, without a Mina
+  /// daemon. Endpoints which need the daemon are rejected. Requires
+  /// `--network-id`.
+  #[arg(
+    long,
+    env = "MINAMESH_ARCHIVE_ONLY",
+    default_value = "false",
+    requires = "network_id",
+    conflicts_with = "offline"
+  )]
+  pub archive_only: bool,
+
+  /// The state hash of the genesis block in archive-only mode. Defaults to the
+  /// oldest block of the Archive Database without a parent.
+  #[arg(long, env = "MINAMESH_GENESIS_STATE_HASH")]
+  pub genesis_state_hash: Option<String>,
+
+  /// The network identifier (e.g. `mina:mainnet`) to validate requests
+  /// against, instead of querying it from the Mina daemon.
+  #[arg(long, env = "MINAMESH_NETWORK_ID")]
+  pub network_id: Option<String>,
+
+  /// The number of blocks the Archive Database may lag behind the Mina
+  /// daemon's best tip before `/health/ready` reports the server as not ready.
+  #[arg(long, env = "MINAMESH_MAX_TIP_DRIFT", default_value_t = 10)]
+  pub max_tip_drift: u32,
+
+  /// The duration (in seconds) after which a request to the Mina GraphQL is
+  /// abandoned.
+  #[arg(long, env = "MINAMESH_GRAPHQL_TIMEOUT", default_value_t = 30)]
+  pub graphql_timeout: u64,
+
+  /// The number of times a failed query to the Mina GraphQL is retried.
+  /// Transaction submissions are never retried.
+  #[arg(long, env = "MINAMESH_GRAPHQL_MAX_RETRIES", default_value_t = 3)]
+  pub graphql_max_retries: u32,
+
+  /// The delay (in milliseconds) before the first retry of a failed query,
+  /// doubled on every subsequent retry.
+  #[arg(long, env = "MINAMESH_GRAPHQL_RETRY_BACKOFF_MS", default_value_t = 200)]
+  pub graphql_retry_backoff_ms: u64,
+
+  /// The number of consecutive failed requests after which requests to the
+  /// Mina GraphQL fail fast. Set to 0 to disable the circuit breaker.
+  #[arg(long, env = "MINAMESH_GRAPHQL_CIRCUIT_BREAKER_THRESHOLD", default_value_t = 5)]
+  pub graphql_circuit_breaker_threshold: u32,
+
+  /// The duration (in seconds) requests fail fast once the circuit breaker
+  /// opens, before the Mina GraphQL is tried again.
+  #[arg(long, env = "MINAMESH_GRAPHQL_CIRCUIT_BREAKER_COOLDOWN", default_value_t = 30)]
+  pub graphql_circuit_breaker_cooldown: u64,
+
+  /// The interval (in seconds) at which the sync status of each Mina GraphQL
+  /// is checked, when more than one is configured.
+  #[arg(long, env = "MINAMESH_GRAPHQL_HEALTH_CHECK_INTERVAL", default_value_t = 10)]
+  pub graphql_health_check_interval: u64,
+
+  /// The number of Mina GraphQL endpoints transaction submissions are
+  /// broadcast to.
+  #[arg(long, env = "MINAMESH_GRAPHQL_BROADCAST", default_value_t = 3)]
+  pub graphql_broadcast: usize,
+}
+
+/// The defaults of the command-line arguments, without the environment.
+impl Default for MinaMeshConfig {
+  fn default() -> Self {
+    Self {
+      proxy_urls: vec![default_mina_proxy_url()],
+      archive_database_url: None,
+      archive_replica_database_url: None,
+      max_replica_lag: 0,
+      block_statement_timeout_ms: 10_000,
+      account_balance_statement_timeout_ms: 10_000,
+      search_transactions_statement_timeout_ms: 30_000,
+      network_id_cache_size: 1,
+      network_id_cache_ttl: 300,
+      transaction_cache_size: 100,
+      transaction_cache_ttl: 0,
+      submission_store_path: None,
+      block_cache_size: 64 * 1024 * 1024,
+      search_count_cache_ttl: 30,
+      max_db_pool_size: 128,
+      db_pool_idle_timeout: 1,
+      use_search_tx_optimizations: false,
+      max_block_transactions: None,
+      offline: false,
+      archive_only: false,
+      genesis_state_hash: None,
+      network_id: None,
+      max_tip_drift: 10,
+      graphql_timeout: 30,
+      graphql_max_retries: 3,
+      graphql_retry_backoff_ms: 200,
+      graphql_circuit_breaker_threshold: 5,
+      graphql_circuit_breaker_cooldown: 30,
+      graphql_health_check_interval: 10,
+      graphql_broadcast: 3,
+    }
+  }
 }
 
 impl MinaMeshConfig {
@@ -53,30 +254,163 @@ impl MinaMeshConfig {
   }
 
   pub async fn to_mina_mesh(self) -> Result<MinaMesh, MinaMeshError> {
-    if self.proxy_url.is_empty() {
+    if self.offline {
+      return self.to_offline_mina_mesh();
+    }
+    if self.archive_only {
+      return self.to_archive_only_mina_mesh().await;
+    }
+    if self.proxy_urls.is_empty() || self.proxy_urls.iter().any(String::is_empty) {
       return Err(MinaMeshError::GraphqlUriNotSet);
     }
-    tracing::info!("Connecting to Mina GraphQL endpoint at {}", self.proxy_url);
-    let graphql_client = GraphQLClient::new(self.proxy_url.to_owned());
+    tracing::info!("Connecting to Mina GraphQL endpoints at {}", self.proxy_urls.join(", "));
+    let graphql_client = GraphQLClient::new(self.proxy_urls.to_owned(), self.graphql_client_policy());
     let res = graphql_client.send(graphql::QueryGenesisBlockIdentifier::build(())).await?;
     let block_height = res.genesis_block.protocol_state.consensus_state.block_height.0.parse::<i64>()?;
     let state_hash = res.genesis_block.state_hash.0.clone();
     tracing::debug!("Genesis block identifier: {}", block_height);
     tracing::debug!("Genesis block state hash: {}", state_hash);
 
+    let archive_database_url = self.archive_database_url.as_deref().unwrap_or_default();
+
     Ok(MinaMesh {
       graphql_client,
-      pg_pool: PgPoolOptions::new()
-        .max_connections(self.max_db_pool_size)
-        .min_connections(0)
-        .idle_timeout(Duration::from_secs(self.db_pool_idle_timeout))
-        .connect(self.archive_database_url.as_str())
-        .await?,
+      pg_pool: self.pg_pool_options().connect_with(PgConnectOptions::from_str(archive_database_url)?).await?,
+      replica_pg_pool: self.replica_pg_pool()?,
+      max_replica_lag: self.max_replica_lag,
+      search_pool: Mutex::default(),
+      statement_timeouts: self.statement_timeouts(),
       genesis_block_identifier: BlockIdentifier::new(block_height, state_hash),
       search_tx_optimized: self.use_search_tx_optimizations,
-      cache: DashMap::new(),
-      cache_ttl: Duration::from_secs(300),
-      cache_tx_size: 100, // Cache limit for last n transactions submitted
+      max_block_transactions: self.max_block_transactions,
+      offline: false,
+      archive_only: false,
+      network_id: self.network_id,
+      max_tip_drift: self.max_tip_drift,
+      cache: self.caches(),
+      submissions: self.submission_store()?,
+    })
+  }
+
+  // The replica is connected lazily, so that an unavailable replica does not
+  // prevent startup: queries fall back to the primary in the meantime.
+  fn replica_pg_pool(&self) -> Result<Option<PgPool>, MinaMeshError> {
+    match self.archive_replica_database_url.as_deref() {
+      Some(url) => Ok(Some(self.pg_pool_options().connect_lazy_with(PgConnectOptions::from_str(url)?))),
+      None => Ok(None),
+    }
+  }
+
+  fn pg_pool_options(&self) -> PgPoolOptions {
+    PgPoolOptions::new()
+      .max_connections(self.max_db_pool_size)
+      .min_connections(0)
+      .idle_timeout(Duration::from_secs(self.db_pool_idle_timeout))
+  }
+
+  fn caches(&self) -> Caches {
+    let ttl = |secs| (secs > 0).then(|| Duration::from_secs(secs));
+    Caches::new(
+      CacheConfig { capacity: self.network_id_cache_size, ttl: ttl(self.network_id_cache_ttl) },
+      self.block_cache_size,
+      CacheConfig {
+        capacity: if self.search_count_cache_ttl > 0 { SEARCH_COUNT_CACHE_SIZE } else { 0 },
+        ttl: ttl(self.search_count_cache_ttl),
+      },
+    )
+  }
+
+  fn submission_store(&self) -> Result<Arc<dyn SubmissionStore>, MinaMeshError> {
+    Ok(match &self.submission_store_path {
+      Some(path) => Arc::new(FileSubmissionStore::open(path)?),
+      None => {
+        let ttl = (self.transaction_cache_ttl > 0).then(|| Duration::from_secs(self.transaction_cache_ttl));
+        Arc::new(MemorySubmissionStore::new(CacheConfig { capacity: self.transaction_cache_size, ttl }))
+      }
+    })
+  }
+
+  fn statement_timeouts(&self) -> StatementTimeouts {
+    let timeout = |millis| (millis > 0).then(|| Duration::from_millis(millis));
+    StatementTimeouts {
+      block: timeout(self.block_statement_timeout_ms),
+      account_balance: timeout(self.account_balance_statement_timeout_ms),
+      search_transactions: timeout(self.search_transactions_statement_timeout_ms),
+    }
+  }
+
+  fn graphql_client_policy(&self) -> GraphQLClientPolicy {
+    GraphQLClientPolicy {
+      timeout: Duration::from_secs(self.graphql_timeout),
+      max_retries: self.graphql_max_retries,
+      retry_backoff: Duration::from_millis(self.graphql_retry_backoff_ms),
+      circuit_breaker_threshold: self.graphql_circuit_breaker_threshold,
+      circuit_breaker_cooldown: Duration::from_secs(self.graphql_circuit_breaker_cooldown),
+      health_check_interval: Duration::from_secs(self.graphql_health_check_interval),
+      broadcast: self.graphql_broadcast,
+    }
+  }
+
+  // Neither the daemon nor the archive is reachable in offline mode: the pool is
+  // created lazily and never connected, as no offline endpoint touches it.
+  fn to_offline_mina_mesh(self) -> Result<MinaMesh, MinaMeshError> {
+    let network_id = self.network_id.ok_or(MinaMeshError::Exception("Offline mode requires a network id".into()))?;
+    tracing::info!("Serving offline endpoints for network {}", network_id);
+    let pg_pool = match self.archive_database_url.as_deref() {
+      Some(url) => PgPoolOptions::new().max_connections(1).connect_lazy(url)?,
+      None => PgPoolOptions::new().max_connections(1).connect_lazy_with(PgConnectOptions::new()),
+    };
+
+    Ok(MinaMesh {
+      graphql_client: GraphQLClient::new(self.proxy_urls.to_owned(), self.graphql_client_policy()),
+      pg_pool,
+      replica_pg_pool: None,
+      max_replica_lag: self.max_replica_lag,
+      search_pool: Mutex::default(),
+      statement_timeouts: self.statement_timeouts(),
+      genesis_block_identifier: BlockIdentifier::default(),
+      search_tx_optimized: false,
+      max_block_transactions: self.max_block_transactions,
+      offline: true,
+      archive_only: false,
+      network_id: Some(network_id),
+      max_tip_drift: self.max_tip_drift,
+      cache: self.caches(),
+      submissions: self.submission_store()?,
+    })
+  }
+
+  // The genesis block is looked up in the archive rather than queried from the
+  // daemon, and the GraphQL client has no endpoint: any daemon query fails.
+  async fn to_archive_only_mina_mesh(self) -> Result<MinaMesh, MinaMeshError> {
+    let network_id =
+      self.network_id.clone().ok_or(MinaMeshError::Exception("Archive-only mode requires a network id".into()))?;
+    tracing::info!("Serving the Data API of network {} from the Archive Database only", network_id);
+    let archive_database_url = self.archive_database_url.as_deref().unwrap_or_default();
+    let pg_pool = self.pg_pool_options().connect_with(PgConnectOptions::from_str(archive_database_url)?).await?;
+    let genesis_block = sqlx::query_file!("sql/queries/genesis_block.sql", self.genesis_state_hash)
+      .fetch_optional(&pg_pool)
+      .await?
+      .ok_or(MinaMeshError::BlockMissing(None, self.genesis_state_hash.clone()))?;
+    tracing::debug!("Genesis block identifier: {}", genesis_block.height);
+    tracing::debug!("Genesis block state hash: {}", genesis_block.state_hash);
+
+    Ok(MinaMesh {
+      graphql_client: GraphQLClient::new(Vec::new(), self.graphql_client_policy()),
+      pg_pool,
+      replica_pg_pool: self.replica_pg_pool()?,
+      max_replica_lag: self.max_replica_lag,
+      search_pool: Mutex::default(),
+      statement_timeouts: self.statement_timeouts(),
+      genesis_block_identifier: BlockIdentifier::new(genesis_block.height, genesis_block.state_hash),
+      search_tx_optimized: self.use_search_tx_optimizations,
+      max_block_transactions: self.max_block_transactions,
+      offline: false,
+      archive_only: true,
+      network_id: Some(network_id),
+      max_tip_drift: self.max_tip_drift,
+      cache: self.caches(),
+      submissions: self.submission_store()?,
     })
   }
 }
@@ -0,0 +1,145 @@
+use std::{
+  env,
+  ffi::OsStr,
+  fs,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Command};
+use serde_json::Value;
+
+use crate::MinaMeshConfig;
+
+/// Fields of [MinaMeshConfig] which may hold credentials.
+const SECRET_KEYS: [&str; 2] = ["archive_database_url", "archive_replica_database_url"];
+
+/// The configuration file, given via `--config` or `MINAMESH_CONFIG`. It is
+/// read before the flags are parsed, so it is looked up in the raw arguments.
+pub fn config_file_path() -> Option<PathBuf> {
+  let mut args = env::args_os().skip(1);
+  while let Some(arg) = args.next() {
+    if arg == "--config" {
+      return args.next().map(PathBuf::from);
+    }
+    if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+      return Some(PathBuf::from(path));
+    }
+  }
+  env::var_os("MINAMESH_CONFIG").map(PathBuf::from)
+}
+
+/// The settings of a configuration file, keyed by the ids of the arguments of
+/// [MinaMeshConfig].
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+  values: Vec<(String, Vec<String>)>,
+}
+
+impl ConfigFile {
+  /// Sets the settings as the defaults of the matching arguments of the
+  /// command and its subcommands, so that flags and environment variables take
+  /// precedence over the file.
+  pub fn apply(&self, mut command: Command) -> Command {
+    for (id, values) in &self.values {
+      if command.get_arguments().any(|arg| arg.get_id() == id.as_str()) {
+        command = command.mut_arg(id, |arg| arg.default_values(values.clone()));
+      }
+    }
+    let subcommands = command.get_subcommands().map(|subcommand| subcommand.get_name().to_string()).collect::<Vec<_>>();
+    for name in subcommands {
+      command = command.mut_subcommand(name, |subcommand| self.apply(subcommand));
+    }
+    command
+  }
+}
+
+/// Loads a TOML or YAML configuration file, keyed by the fields of
+/// [MinaMeshConfig] or their flags.
+pub fn load_config_file(path: &Path) -> Result<ConfigFile> {
+  let contents = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+  let values: Value = match path.extension().and_then(OsStr::to_str) {
+    Some("toml") => toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))?,
+    Some("yaml" | "yml") => {
+      serde_yaml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))?
+    }
+    _ => bail!("Unsupported configuration file {}, expected .toml, .yaml or .yml", path.display()),
+  };
+  let Value::Object(values) = values else {
+    bail!("The configuration file {} must contain a table of settings", path.display());
+  };
+
+  let command = MinaMeshConfig::augment_args(Command::new("mina-mesh"));
+  let values = values
+    .into_iter()
+    .map(|(key, value)| {
+      let arg = command
+        .get_arguments()
+        .find(|arg| arg.get_id() == key.as_str() || arg.get_long() == Some(key.as_str()))
+        .ok_or_else(|| anyhow!("Unknown setting {} in {}", key, path.display()))?;
+      Ok((arg.get_id().to_string(), arg_values(&key, value)?))
+    })
+    .collect::<Result<_>>()?;
+  Ok(ConfigFile { values })
+}
+
+// Lists give one value each to multi-valued flags.
+fn arg_values(key: &str, value: Value) -> Result<Vec<String>> {
+  match value {
+    Value::String(value) => Ok(vec![value]),
+    Value::Bool(_) | Value::Number(_) => Ok(vec![value.to_string()]),
+    Value::Array(values) => values
+      .into_iter()
+      .map(|value| match value {
+        Value::String(value) => Ok(value),
+        Value::Bool(_) | Value::Number(_) => Ok(value.to_string()),
+        _ => bail!("Setting {} must be a list of strings, numbers or booleans", key),
+      })
+      .collect(),
+    Value::Null | Value::Object(_) => bail!("Setting {} must be a string, number, boolean or list", key),
+  }
+}
+
+impl MinaMeshConfig {
+  /// The configuration as TOML, with the credentials of database URLs
+  /// redacted and unset settings omitted.
+  pub fn to_redacted_toml(&self) -> Result<String> {
+    let Value::Object(mut values) = serde_json::to_value(self)? else {
+      bail!("The configuration does not serialize to a table");
+    };
+    values.retain(|_, value| !value.is_null());
+    for key in SECRET_KEYS {
+      if let Some(Value::String(url)) = values.get_mut(key) {
+        *url = redact_url(url);
+      }
+    }
+    Ok(toml::to_string(&values)?)
+  }
+}
+
+// URLs which cannot be parsed are redacted entirely, as they may hold
+// credentials anywhere.
+fn redact_url(url: &str) -> String {
+  match reqwest::Url::parse(url) {
+    Ok(mut url) => {
+      if url.password().is_some() {
+        let _ = url.set_password(Some("REDACTED"));
+      }
+      let query = url
+        .query_pairs()
+        .map(|(name, value)| {
+          if name == "password" {
+            (name.to_string(), "REDACTED".to_string())
+          } else {
+            (name.to_string(), value.to_string())
+          }
+        })
+        .collect::<Vec<_>>();
+      if !query.is_empty() {
+        url.query_pairs_mut().clear().extend_pairs(query);
+      }
+      url.to_string()
+    }
+    Err(_) => "REDACTED".to_string(),
+  }
+}
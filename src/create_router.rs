@@ -2,40 +2,151 @@ use std::sync::Arc;
 
 use axum::{
   debug_handler,
-  extract::State,
-  response::IntoResponse,
-  routing::{get, post},
+  extract::{Request, State},
+  http::{header, StatusCode},
+  middleware::{self, Next},
+  response::{IntoResponse, Response},
+  routing::{get, post, MethodRouter},
   Json, Router,
 };
 use paste::paste;
+use serde_json::json;
 
-use crate::{playground::handle_playground, util::Wrapper, MinaMesh, MinaMeshError};
+use crate::{
+  metrics, openapi, playground::handle_playground, util::Wrapper, MinaMesh, MinaMeshError,
+  SearchTransactionsPageRequest,
+};
+
+/// Endpoints which need neither the Mina daemon nor the Archive Database. The
+/// network endpoints only need the configured network id.
+const OFFLINE_ENDPOINTS: [&str; 8] = [
+  "/construction/derive",
+  "/construction/combine",
+  "/construction/hash",
+  "/construction/parse",
+  "/construction/payloads",
+  "/construction/preprocess",
+  "/network/list",
+  "/network/options",
+];
+
+/// Endpoints served from the Archive Database alone, in addition to the
+/// offline ones, in archive-only mode.
+const ARCHIVE_ENDPOINTS: [&str; 6] =
+  ["/account/balance", "/block", "/block/transaction", "/events/blocks", "/network/status", "/search/transactions"];
+
+/// Endpoints outside of the Mesh spec, served in every mode.
+const OPERATIONAL_ENDPOINTS: [&str; 5] =
+  ["/available_endpoints", "/health/live", "/health/ready", "/metrics", "/openapi.json"];
+
+/// A Mesh endpoint, with the names of the schemas of its request and response
+/// as documented in `/openapi.json`.
+pub(crate) struct MeshRoute {
+  pub path: &'static str,
+  pub request: &'static str,
+  pub response: &'static str,
+  handler: MethodRouter<Arc<MinaMesh>>,
+}
+
+/// The Mesh endpoints registered by [create_router].
+pub(crate) fn mesh_routes() -> Vec<MeshRoute> {
+  macro_rules! route {
+    ($path:literal, $handler:ident, $request:literal, $response:literal) => {
+      MeshRoute { path: $path, request: $request, response: $response, handler: post($handler) }
+    };
+  }
+  vec![
+    route!("/account/balance", handle_account_balance, "AccountBalanceRequest", "AccountBalanceResponse"),
+    route!("/block", handle_block, "BlockRequest", "BlockResponse"),
+    route!("/block/transaction", handle_block_transaction, "BlockTransactionRequest", "BlockTransactionResponse"),
+    route!("/call", handle_call, "CallRequest", "CallResponse"),
+    route!(
+      "/construction/combine",
+      handle_construction_combine,
+      "ConstructionCombineRequest",
+      "ConstructionCombineResponse"
+    ),
+    route!(
+      "/construction/derive",
+      handle_construction_derive,
+      "ConstructionDeriveRequest",
+      "ConstructionDeriveResponse"
+    ),
+    route!("/construction/hash", handle_construction_hash, "ConstructionHashRequest", "TransactionIdentifierResponse"),
+    route!(
+      "/construction/metadata",
+      handle_construction_metadata,
+      "ConstructionMetadataRequest",
+      "ConstructionMetadataResponse"
+    ),
+    route!("/construction/parse", handle_construction_parse, "ConstructionParseRequest", "ConstructionParseResponse"),
+    route!(
+      "/construction/payloads",
+      handle_construction_payloads,
+      "ConstructionPayloadsRequest",
+      "ConstructionPayloadsResponse"
+    ),
+    route!(
+      "/construction/preprocess",
+      handle_construction_preprocess,
+      "ConstructionPreprocessRequest",
+      "ConstructionPreprocessResponse"
+    ),
+    route!("/construction/submit", handle_construction_submit, "ConstructionSubmitRequest", "TransactionIdentifier"),
+    route!("/events/blocks", handle_events_blocks, "EventsBlocksRequest", "EventsBlocksResponse"),
+    route!("/mempool", handle_mempool, "NetworkRequest", "MempoolResponse"),
+    route!(
+      "/mempool/transaction",
+      handle_mempool_transaction,
+      "MempoolTransactionRequest",
+      "MempoolTransactionResponse"
+    ),
+    route!("/network/list", handle_network_list, "MetadataRequest", "NetworkListResponse"),
+    route!("/network/options", handle_network_options, "NetworkRequest", "NetworkOptionsResponse"),
+    route!("/network/status", handle_network_status, "NetworkRequest", "NetworkStatusResponse"),
+    route!(
+      "/search/transactions",
+      handle_search_transactions,
+      "SearchTransactionsRequest",
+      "SearchTransactionsResponse"
+    ),
+  ]
+}
+
+/// Whether the Mesh endpoint is served in the mode of the server.
+pub(crate) fn is_served(mina_mesh: &MinaMesh, path: &str) -> bool {
+  if mina_mesh.offline {
+    OFFLINE_ENDPOINTS.contains(&path)
+  } else if mina_mesh.archive_only {
+    OFFLINE_ENDPOINTS.contains(&path) || ARCHIVE_ENDPOINTS.contains(&path)
+  } else {
+    true
+  }
+}
 
 pub fn create_router(mina_mesh: MinaMesh, playground: bool) -> Router {
+  let (offline, archive_only) = (mina_mesh.offline, mina_mesh.archive_only);
+  metrics::prometheus_handle();
   let mut router = Router::new()
     .route("/available_endpoints", get(handle_available_endpoints))
-    .route("/account/balance", post(handle_account_balance))
-    .route("/block", post(handle_block))
-    .route("/call", post(handle_call))
-    .route("/construction/combine", post(handle_construction_combine))
-    .route("/construction/derive", post(handle_construction_derive))
-    .route("/construction/hash", post(handle_construction_hash))
-    .route("/construction/metadata", post(handle_construction_metadata))
-    .route("/construction/parse", post(handle_construction_parse))
-    .route("/construction/payloads", post(handle_construction_payloads))
-    .route("/construction/preprocess", post(handle_construction_preprocess))
-    .route("/construction/submit", post(handle_construction_submit))
-    .route("/mempool", post(handle_mempool))
-    .route("/mempool/transaction", post(handle_mempool_transaction))
-    .route("/network/list", post(handle_network_list))
-    .route("/network/options", post(handle_network_options))
-    .route("/network/status", post(handle_network_status))
-    .route("/search/transactions", post(handle_search_transactions))
-    .with_state(Arc::new(mina_mesh));
+    .route("/health/live", get(handle_health_live))
+    .route("/health/ready", get(handle_health_ready))
+    .route("/metrics", get(handle_metrics))
+    .route("/openapi.json", get(handle_openapi));
+  for MeshRoute { path, handler, .. } in mesh_routes() {
+    router = router.route(path, handler);
+  }
+  let mut router = router.with_state(Arc::new(mina_mesh));
+  if offline {
+    router = router.layer(middleware::from_fn(reject_online_endpoints));
+  }
+  if archive_only {
+    router = router.layer(middleware::from_fn(reject_daemon_endpoints));
+  }
   if playground {
     router = router.route("/", get(handle_playground));
   }
-  router
+  router.route_layer(middleware::from_fn(metrics::track_requests))
 }
 
 macro_rules! create_handler {
@@ -60,6 +171,7 @@ macro_rules! create_handler {
 
 create_handler!(account_balance, AccountBalanceRequest);
 create_handler!(block, BlockRequest);
+create_handler!(block_transaction, BlockTransactionRequest);
 create_handler!(call, CallRequest);
 create_handler!(construction_combine, ConstructionCombineRequest);
 create_handler!(construction_derive, ConstructionDeriveRequest);
@@ -69,31 +181,67 @@ create_handler!(construction_parse, ConstructionParseRequest);
 create_handler!(construction_payloads, ConstructionPayloadsRequest);
 create_handler!(construction_preprocess, ConstructionPreprocessRequest);
 create_handler!(construction_submit, ConstructionSubmitRequest);
+create_handler!(events_blocks, EventsBlocksRequest);
 create_handler!(mempool, NetworkRequest);
 create_handler!(mempool_transaction, MempoolTransactionRequest);
 create_handler!(network_list);
 create_handler!(network_options, NetworkRequest);
 create_handler!(network_status, NetworkRequest);
-create_handler!(search_transactions, SearchTransactionsRequest);
+
+// Takes the Mesh request along with Mina's `cursor`
+async fn handle_search_transactions(
+  mina_mesh: State<Arc<MinaMesh>>,
+  req: Result<Json<SearchTransactionsPageRequest>, axum::extract::rejection::JsonRejection>,
+) -> impl IntoResponse {
+  match req {
+    Ok(Json(req)) => Wrapper(mina_mesh.search_transactions_page(req).await),
+    Err(err) => Wrapper(Err(MinaMeshError::from(err))),
+  }
+}
+
+async fn reject_online_endpoints(request: Request, next: Next) -> Response {
+  reject_unserved_endpoints(request, next, &[&OFFLINE_ENDPOINTS], "offline").await
+}
+
+async fn reject_daemon_endpoints(request: Request, next: Next) -> Response {
+  reject_unserved_endpoints(request, next, &[&OFFLINE_ENDPOINTS, &ARCHIVE_ENDPOINTS], "archive-only").await
+}
+
+async fn reject_unserved_endpoints(request: Request, next: Next, served: &[&[&str]], mode: &str) -> Response {
+  let path = request.uri().path();
+  if OPERATIONAL_ENDPOINTS.contains(&path) || served.iter().any(|endpoints| endpoints.contains(&path)) {
+    return next.run(request).await;
+  }
+  MinaMeshError::EndpointUnavailable(format!("{} is not served in {} mode", path, mode)).into_response()
+}
+
+#[debug_handler]
+async fn handle_health_live() -> impl IntoResponse {
+  Json(json!({ "status": "ok" }))
+}
+
+#[debug_handler]
+async fn handle_health_ready(mina_mesh: State<Arc<MinaMesh>>) -> impl IntoResponse {
+  let report = mina_mesh.health_ready().await;
+  let status = if report.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+  (status, Json(report))
+}
+
+/// The OpenAPI document of the endpoints served in the mode of the server,
+/// which the playground renders.
+#[debug_handler]
+async fn handle_openapi(mina_mesh: State<Arc<MinaMesh>>) -> impl IntoResponse {
+  Json(openapi::document(&mina_mesh))
+}
+
+/// Served as plain text in the Prometheus exposition format, outside of the
+/// Mesh JSON envelope.
+#[debug_handler]
+async fn handle_metrics(mina_mesh: State<Arc<MinaMesh>>) -> impl IntoResponse {
+  ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics::render(&mina_mesh))
+}
 
 #[debug_handler]
-async fn handle_available_endpoints() -> impl IntoResponse {
-  Json([
-    "/account/balance",
-    "/construction/derive",
-    "/construction/combine",
-    "/construction/hash",
-    "/construction/parse",
-    "/construction/payloads",
-    "/construction/preprocess",
-    "/construction/metadata",
-    "/construction/submit",
-    "/block",
-    "/mempool",
-    "/mempool/transaction",
-    "/network/list",
-    "/network/options",
-    "/network/status",
-    "/search/transactions",
-  ])
+async fn handle_available_endpoints(mina_mesh: State<Arc<MinaMesh>>) -> impl IntoResponse {
+  Json(mesh_routes().into_iter().map(|route| route.path).filter(|path| is_served(&mina_mesh, path)).collect::<Vec<_>>())
 }
@@ -91,6 +91,15 @@ pub enum MinaMeshError {
 
   #[error("Can't send transaction: Expired")]
   TransactionSubmitExpired(String),
+
+  #[error("Endpoint unavailable: {0}")]
+  EndpointUnavailable(String),
+
+  #[error("SQL statement timeout: {0}")]
+  SqlTimeout(String),
+
+  #[error("Invalid cursor: {0}")]
+  InvalidCursor(String),
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize)]
@@ -137,6 +146,9 @@ impl MinaMeshError {
       MinaMeshError::TransactionSubmitInvalidSignature("Invalid signature".to_string()),
       MinaMeshError::TransactionSubmitInsufficientBalance("Insufficient balance".to_string()),
       MinaMeshError::TransactionSubmitExpired("Expired transaction".to_string()),
+      MinaMeshError::EndpointUnavailable("/block is not served in offline mode".to_string()),
+      MinaMeshError::SqlTimeout("canceling statement due to statement timeout".to_string()),
+      MinaMeshError::InvalidCursor("A cursor cannot be combined with an offset".to_string()),
     ]
   }
 
@@ -169,6 +181,9 @@ impl MinaMeshError {
       MinaMeshError::TransactionSubmitInvalidSignature(_) => 24,
       MinaMeshError::TransactionSubmitInsufficientBalance(_) => 25,
       MinaMeshError::TransactionSubmitExpired(_) => 26,
+      MinaMeshError::EndpointUnavailable(_) => 27,
+      MinaMeshError::SqlTimeout(_) => 28,
+      MinaMeshError::InvalidCursor(_) => 29,
     }
   }
 
@@ -182,6 +197,7 @@ impl MinaMeshError {
         | MinaMeshError::TransactionNotFound(_)
         | MinaMeshError::BlockMissing(_, _)
         | MinaMeshError::ChainInfoMissing
+        | MinaMeshError::SqlTimeout(_)
     )
   }
 
@@ -247,6 +263,16 @@ impl MinaMeshError {
       MinaMeshError::TransactionSubmitExpired(err) => json!({
         "error": err,
       }),
+      MinaMeshError::EndpointUnavailable(err) => json!({
+        "error": err,
+      }),
+      MinaMeshError::SqlTimeout(msg) => json!({
+        "error": msg,
+        "extra": "Internal SQL query exceeded its statement timeout"
+      }),
+      MinaMeshError::InvalidCursor(err) => json!({
+        "error": err,
+      }),
       MinaMeshError::OperationsNotValid(reasons) => json!({
         "error": "We could not convert those operations to a valid transaction.",
         "reasons": reasons,
@@ -338,6 +364,13 @@ impl MinaMeshError {
       MinaMeshError::TransactionSubmitExpired(_) => {
         "This transaction is expired. Please try again with a larger valid_until.".to_string()
       }
+      MinaMeshError::EndpointUnavailable(_) => {
+        "This endpoint is not available with the current server configuration.".to_string()
+      }
+      MinaMeshError::SqlTimeout(_) => "A SQL query took too long and was cancelled.".to_string(),
+      MinaMeshError::InvalidCursor(_) => {
+        "The search cursor is malformed or cannot be used with the other parameters of the request.".to_string()
+      }
     }
   }
 }
@@ -371,8 +404,12 @@ impl IntoResponse for MinaMeshError {
       MinaMeshError::TransactionSubmitInvalidSignature(_) => StatusCode::BAD_REQUEST,
       MinaMeshError::TransactionSubmitInsufficientBalance(_) => StatusCode::BAD_REQUEST,
       MinaMeshError::TransactionSubmitExpired(_) => StatusCode::BAD_REQUEST,
+      MinaMeshError::EndpointUnavailable(_) => StatusCode::NOT_IMPLEMENTED,
+      MinaMeshError::SqlTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+      MinaMeshError::InvalidCursor(_) => StatusCode::BAD_REQUEST,
     };
 
+    crate::metrics::record_error(self.error_code());
     let body = json!({
         "code": self.error_code(),
         "message": self.to_string(),
@@ -388,7 +425,11 @@ impl IntoResponse for MinaMeshError {
 /// Implement `From` conversions for third-party errors.
 impl From<SqlxError> for MinaMeshError {
   fn from(value: SqlxError) -> Self {
-    MinaMeshError::Sql(value.to_string())
+    match &value {
+      // query_canceled, raised once the `statement_timeout` elapses
+      SqlxError::Database(err) if err.code().as_deref() == Some("57014") => MinaMeshError::SqlTimeout(err.to_string()),
+      _ => MinaMeshError::Sql(value.to_string()),
+    }
   }
 }
 
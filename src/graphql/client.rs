@@ -1,19 +1,151 @@
-use cynic::http::ReqwestExt;
+use std::{
+  cmp::Reverse,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use cynic::GraphQlResponse;
+use futures::future::join_all;
 use reqwest::Client;
+use serde_json::{json, Value};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use crate::{metrics, MinaMeshError};
+
+const ENDPOINT_HEALTH: &str =
+  "query EndpointHealth { syncStatus bestChain(maxLength: 1) { protocolState { consensusState { blockHeight } } } }";
+
+/// Timeout, retry, circuit-breaker and failover settings of the
+/// [GraphQLClient].
+#[derive(Debug, Clone)]
+pub struct GraphQLClientPolicy {
+  /// Timeout of a single attempt, including reading the response body.
+  pub timeout: Duration,
+  /// Retries of a failed query after the first attempt. Mutations are never
+  /// retried, as the daemon may have applied them before failing.
+  pub max_retries: u32,
+  /// Delay before the first retry, doubled on every subsequent one.
+  pub retry_backoff: Duration,
+  /// Consecutive failed attempts after which requests to an endpoint fail
+  /// fast. Zero disables the circuit breaker.
+  pub circuit_breaker_threshold: u32,
+  /// How long requests to an endpoint fail fast before it is tried again.
+  pub circuit_breaker_cooldown: Duration,
+  /// How often the sync status of each endpoint is refreshed, when there is
+  /// more than one to choose from.
+  pub health_check_interval: Duration,
+  /// The number of endpoints mutations are broadcast to.
+  pub broadcast: usize,
+}
+
+impl Default for GraphQLClientPolicy {
+  fn default() -> Self {
+    Self {
+      timeout: Duration::from_secs(30),
+      max_retries: 3,
+      retry_backoff: Duration::from_millis(200),
+      circuit_breaker_threshold: 5,
+      circuit_breaker_cooldown: Duration::from_secs(30),
+      health_check_interval: Duration::from_secs(10),
+      broadcast: 3,
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+  consecutive_failures: u32,
+  open_until: Option<Instant>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct EndpointHealth {
+  synced: bool,
+  height: Option<i64>,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+  url: String,
+  /// The position of the endpoint in the configuration, which labels its
+  /// metrics instead of the URL, as the latter may hold credentials.
+  label: String,
+  circuit_breaker: Mutex<CircuitBreaker>,
+  health: Mutex<EndpointHealth>,
+}
+
+impl Endpoint {
+  fn new(index: usize, url: String) -> Self {
+    Self {
+      url,
+      label: index.to_string(),
+      circuit_breaker: Mutex::new(CircuitBreaker::default()),
+      health: Mutex::new(EndpointHealth::default()),
+    }
+  }
+
+  fn health(&self) -> EndpointHealth {
+    *self.health.lock().expect("Endpoint health lock poisoned")
+  }
+
+  fn check_circuit_breaker(&self) -> Result<(), String> {
+    let circuit_breaker = self.circuit_breaker.lock().expect("Circuit breaker lock poisoned");
+    let now = Instant::now();
+    match circuit_breaker.open_until {
+      Some(open_until) if now < open_until => Err(format!(
+        "Mina daemon at {} unavailable after {} consecutive failures, retrying in {:?}",
+        self.url,
+        circuit_breaker.consecutive_failures,
+        open_until - now
+      )),
+      _ => Ok(()),
+    }
+  }
+
+  fn record_success(&self) {
+    let mut circuit_breaker = self.circuit_breaker.lock().expect("Circuit breaker lock poisoned");
+    *circuit_breaker = CircuitBreaker::default();
+  }
 
-use crate::MinaMeshError;
+  // Once open, a single failure after the cooldown opens the circuit again.
+  fn record_failure(&self, policy: &GraphQLClientPolicy) {
+    let mut circuit_breaker = self.circuit_breaker.lock().expect("Circuit breaker lock poisoned");
+    circuit_breaker.consecutive_failures += 1;
+    let threshold = policy.circuit_breaker_threshold;
+    if threshold > 0 && circuit_breaker.consecutive_failures >= threshold {
+      circuit_breaker.open_until = Some(Instant::now() + policy.circuit_breaker_cooldown);
+    }
+  }
+}
 
 #[derive(Debug)]
 pub struct GraphQLClient {
-  mina_proxy_url: String,
+  endpoints: Arc<[Endpoint]>,
   client: Client,
+  policy: GraphQLClientPolicy,
+  health_checks: Option<JoinHandle<()>>,
 }
 
 impl GraphQLClient {
-  pub fn new(mina_proxy_url: String) -> Self {
-    Self { mina_proxy_url, client: Client::new() }
+  /// Creates the client, checking the health of the endpoints in the
+  /// background when there is more than one to rank.
+  pub fn new(mina_proxy_urls: Vec<String>, policy: GraphQLClientPolicy) -> Self {
+    let client = Client::builder().timeout(policy.timeout).build().expect("Failed to build the GraphQL HTTP client");
+    let endpoints = mina_proxy_urls
+      .into_iter()
+      .enumerate()
+      .map(|(index, url)| Endpoint::new(index, url))
+      .collect::<Arc<[Endpoint]>>();
+    let health_checks = (endpoints.len() > 1).then(|| {
+      tokio::spawn(check_health_periodically(endpoints.clone(), client.clone(), policy.health_check_interval))
+    });
+    Self { endpoints, client, policy, health_checks }
   }
 
+  /// Sends the operation to the best synced endpoint. Queries are retried with
+  /// exponential backoff on transport failures, failing over to the next best
+  /// endpoint whose circuit is closed, and fail at once if none is. Mutations are broadcast to several endpoints at once and never
+  /// retried. GraphQL errors returned by the daemon are never retried.
   pub async fn send<ResponseData, Vars>(
     &self,
     operation: cynic::Operation<ResponseData, Vars>,
@@ -22,18 +154,17 @@ impl GraphQLClient {
     Vars: serde::Serialize + derive_more::Debug,
     ResponseData: serde::de::DeserializeOwned + 'static + derive_more::Debug,
   {
-    tracing::debug!("GraphQL request to: {}, with variables: {:?}", self.mina_proxy_url, operation.variables);
-
-    let response = match self.client.post(self.mina_proxy_url.to_owned()).run_graphql(operation).await {
-      Ok(resp) => {
-        tracing::debug!("GraphQL Raw Response: {:?}", resp);
-        resp
-      }
-      Err(e) => {
-        tracing::error!("GraphQL Request Failed: {:?}", e);
-        return Err(MinaMeshError::GraphqlMinaQuery(e.to_string()));
-      }
-    };
+    if self.endpoints.is_empty() {
+      return Err(MinaMeshError::GraphqlUriNotSet);
+    }
+    let body = serde_json::to_value(&operation).map_err(|e| MinaMeshError::GraphqlMinaQuery(e.to_string()))?;
+    let endpoints = self.ranked_endpoints();
+    let response = if operation.query.trim_start().starts_with("mutation") {
+      self.broadcast(&endpoints, &body, &operation.variables).await
+    } else {
+      self.query(&endpoints, &body, &operation.variables).await
+    }
+    .map_err(MinaMeshError::GraphqlMinaQuery)?;
 
     if let Some(errors) = response.errors {
       Err(MinaMeshError::GraphqlMinaQuery(errors.into_iter().map(|err| err.message).collect::<Vec<_>>().join("\n\n")))
@@ -43,4 +174,170 @@ impl GraphQLClient {
       Err(MinaMeshError::GraphqlMinaQuery("".to_string()))
     }
   }
+
+  async fn query<ResponseData>(
+    &self,
+    endpoints: &[&Endpoint],
+    body: &Value,
+    variables: &impl derive_more::Debug,
+  ) -> Result<GraphQlResponse<ResponseData>, String>
+  where
+    ResponseData: serde::de::DeserializeOwned + derive_more::Debug,
+  {
+    let mut backoff = self.policy.retry_backoff;
+    let mut attempt = 0;
+    loop {
+      let endpoint = closed_endpoint(endpoints, attempt as usize).inspect_err(|e| {
+        tracing::error!("GraphQL Request Failed: {}", e);
+      })?;
+      tracing::debug!("GraphQL request to: {}, with variables: {:?}", endpoint.url, variables);
+      match self.send_to(endpoint, body).await {
+        Ok(response) => return Ok(response),
+        Err(e) if attempt >= self.policy.max_retries => {
+          tracing::error!("GraphQL Request Failed: {}", e);
+          return Err(e);
+        }
+        Err(e) => {
+          attempt += 1;
+          // Once every circuit is open, the next attempt fails fast instead of
+          // waiting for the backoff
+          if closed_endpoint(endpoints, attempt as usize).is_err() {
+            continue;
+          }
+          tracing::warn!("GraphQL Request Failed (attempt {}), retrying in {:?}: {}", attempt, backoff, e);
+          tokio::time::sleep(backoff).await;
+          backoff *= 2;
+        }
+      }
+    }
+  }
+
+  /// Sends the mutation to the best endpoints concurrently. A response
+  /// accepted without errors wins, otherwise the best endpoint's response is
+  /// returned so that its errors can be reported.
+  async fn broadcast<ResponseData>(
+    &self,
+    endpoints: &[&Endpoint],
+    body: &Value,
+    variables: &impl derive_more::Debug,
+  ) -> Result<GraphQlResponse<ResponseData>, String>
+  where
+    ResponseData: serde::de::DeserializeOwned + derive_more::Debug,
+  {
+    let targets = &endpoints[..self.policy.broadcast.clamp(1, endpoints.len())];
+    for endpoint in targets {
+      tracing::debug!("GraphQL mutation to: {}, with variables: {:?}", endpoint.url, variables);
+    }
+    let mut responses = join_all(targets.iter().map(|endpoint| self.send_to(endpoint, body))).await;
+    let best = responses
+      .iter()
+      .position(|response| matches!(response, Ok(response) if response.errors.is_none()))
+      .or_else(|| responses.iter().position(Result::is_ok))
+      .unwrap_or_default();
+    let response = responses.swap_remove(best);
+    if let Err(e) = &response {
+      tracing::error!("GraphQL Request Failed: {}", e);
+    }
+    response
+  }
+
+  async fn send_to<ResponseData>(
+    &self,
+    endpoint: &Endpoint,
+    body: &Value,
+  ) -> Result<GraphQlResponse<ResponseData>, String>
+  where
+    ResponseData: serde::de::DeserializeOwned + derive_more::Debug,
+  {
+    endpoint.check_circuit_breaker()?;
+    let start = Instant::now();
+    match post::<ResponseData>(&self.client, &endpoint.url, body).await {
+      Ok(response) => {
+        tracing::debug!("GraphQL Raw Response: {:?}", response);
+        metrics::record_graphql_request(&endpoint.label, start, response.errors.is_some() || response.data.is_none());
+        endpoint.record_success();
+        Ok(response)
+      }
+      Err(e) => {
+        metrics::record_graphql_request(&endpoint.label, start, true);
+        endpoint.record_failure(&self.policy);
+        Err(format!("{}: {}", endpoint.url, e))
+      }
+    }
+  }
+
+  /// The endpoints from best to worst: synced ones first, the highest tip
+  /// first, then in the configured order.
+  fn ranked_endpoints(&self) -> Vec<&Endpoint> {
+    let mut endpoints = self.endpoints.iter().map(|endpoint| (endpoint, endpoint.health())).collect::<Vec<_>>();
+    endpoints.sort_by_key(|(_, health)| (Reverse(health.synced), Reverse(health.height)));
+    endpoints.into_iter().map(|(endpoint, _)| endpoint).collect()
+  }
+}
+
+impl Drop for GraphQLClient {
+  fn drop(&mut self) {
+    if let Some(health_checks) = &self.health_checks {
+      health_checks.abort();
+    }
+  }
+}
+
+/// The first endpoint whose circuit is closed, starting from the one at
+/// `start` and wrapping around, or the error of the first open circuit if all
+/// of them are open.
+fn closed_endpoint<'a>(endpoints: &[&'a Endpoint], start: usize) -> Result<&'a Endpoint, String> {
+  let mut unavailable = None;
+  for endpoint in endpoints.iter().cycle().skip(start % endpoints.len()).take(endpoints.len()) {
+    match endpoint.check_circuit_breaker() {
+      Ok(()) => return Ok(endpoint),
+      Err(e) => {
+        unavailable.get_or_insert(e);
+      }
+    }
+  }
+  Err(unavailable.unwrap_or_default())
+}
+
+async fn post<ResponseData>(client: &Client, url: &str, body: &Value) -> Result<GraphQlResponse<ResponseData>, String>
+where
+  ResponseData: serde::de::DeserializeOwned,
+{
+  let response = client.post(url).json(body).send().await.map_err(|e| e.to_string())?;
+  let status = response.status();
+  if !status.is_success() {
+    let text = response.text().await.unwrap_or_default();
+    return Err(format!("HTTP {}: {}", status, text));
+  }
+  response.json::<GraphQlResponse<ResponseData>>().await.map_err(|e| e.to_string())
+}
+
+/// Refreshes the sync status of every endpoint on the interval, off the request
+/// path. Health checks bypass the circuit breakers, which only count failed
+/// requests.
+async fn check_health_periodically(endpoints: Arc<[Endpoint]>, client: Client, interval: Duration) {
+  let mut interval = tokio::time::interval(interval.max(Duration::from_secs(1)));
+  interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+  loop {
+    interval.tick().await;
+    join_all(endpoints.iter().map(|endpoint| check_health(&client, endpoint))).await;
+  }
+}
+
+async fn check_health(client: &Client, endpoint: &Endpoint) {
+  let response = post::<Value>(client, &endpoint.url, &json!({ "query": ENDPOINT_HEALTH })).await;
+  let data = response.ok().and_then(|response| response.data);
+  let synced = data.as_ref().is_some_and(|data| data["syncStatus"] == "SYNCED");
+  let height = data
+    .as_ref()
+    .and_then(|data| data.pointer("/bestChain/0/protocolState/consensusState/blockHeight"))
+    .and_then(Value::as_str)
+    .and_then(|height| height.parse::<i64>().ok());
+  if !synced {
+    tracing::warn!("Mina daemon at {} is not synced", endpoint.url);
+  }
+  metrics::record_graphql_endpoint_health(&endpoint.label, synced);
+  let mut health = endpoint.health.lock().expect("Endpoint health lock poisoned");
+  health.synced = synced;
+  health.height = height;
 }
@@ -1,39 +1,56 @@
 mod api;
+mod cache;
 mod commands;
 mod config;
+mod config_file;
 mod create_router;
 mod error;
 mod graphql;
 pub mod memo;
+mod metrics;
+mod openapi;
 mod playground;
 mod roinput;
 pub mod signer_utils;
+mod submission_store;
 pub mod test;
 mod transaction_operations;
 mod types;
 pub mod util;
+mod zkapp;
 
-use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex};
 
+pub use cache::*;
 pub use coinbase_mesh::models;
 use coinbase_mesh::models::BlockIdentifier;
 pub use commands::*;
 pub use config::*;
+pub use config_file::*;
 pub use create_router::create_router;
-use dashmap::DashMap;
 pub use error::*;
 use graphql::GraphQLClient;
 pub(crate) use roinput::*;
 use sqlx::PgPool;
+pub use submission_store::*;
 pub use transaction_operations::*;
 pub use types::*;
+pub use zkapp::*;
 #[derive(Debug)]
 pub struct MinaMesh {
   pub graphql_client: GraphQLClient,
   pub pg_pool: PgPool,
+  pub replica_pg_pool: Option<PgPool>,
+  pub max_replica_lag: u32,
+  pub search_pool: Mutex<Option<SearchPool>>,
+  pub statement_timeouts: StatementTimeouts,
   pub genesis_block_identifier: BlockIdentifier,
   pub search_tx_optimized: bool,
-  pub cache: DashMap<String, (String, Instant)>, // Cache for network_id or other reusable data
-  pub cache_ttl: Duration,                       /* Cache time-to-live (network_id is refreshed after this time) */
-  pub cache_tx_size: usize,                      // Cache limit for last n transactions submitted
+  pub max_block_transactions: Option<usize>,
+  pub offline: bool,
+  pub archive_only: bool,
+  pub network_id: Option<String>,
+  pub max_tip_drift: u32,
+  pub cache: Caches,
+  pub submissions: Arc<dyn SubmissionStore>,
 }
@@ -1,9 +1,10 @@
-use crate::MinaMeshError;
+use crate::{signer_utils::encode_base58_with_checksum, MinaMeshError};
 
 const MEMO_LENGTH: usize = 34;
 const TAG_INDEX: usize = 0;
 const LENGTH_INDEX: usize = 1;
 const BYTES_TAG: u8 = 0x01;
+const BASE58_VERSION_BYTE: u8 = 0x14;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Memo(pub [u8; MEMO_LENGTH]);
@@ -39,6 +40,12 @@ impl Memo {
     let len = self.0[LENGTH_INDEX] as usize;
     String::from_utf8_lossy(&self.0[2..2 + len]).into_owned()
   }
+
+  /// Base58check encoding of the memo, as used by the daemon's GraphQL API.
+  /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/lib/mina_base/signed_command_memo.ml#L41
+  pub fn to_base58(&self) -> String {
+    encode_base58_with_checksum(BASE58_VERSION_BYTE, &self.0)
+  }
 }
 
 #[cfg(test)]
@@ -0,0 +1,104 @@
+use std::{sync::OnceLock, time::Instant};
+
+use axum::{
+  extract::{MatchedPath, Request},
+  middleware::Next,
+  response::Response,
+};
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::MinaMesh;
+
+const HTTP_REQUESTS_TOTAL: &str = "mina_mesh_http_requests_total";
+const HTTP_REQUEST_DURATION_SECONDS: &str = "mina_mesh_http_request_duration_seconds";
+const ERRORS_TOTAL: &str = "mina_mesh_errors_total";
+const GRAPHQL_REQUESTS_TOTAL: &str = "mina_mesh_graphql_requests_total";
+const GRAPHQL_FAILURES_TOTAL: &str = "mina_mesh_graphql_failures_total";
+const GRAPHQL_REQUEST_DURATION_SECONDS: &str = "mina_mesh_graphql_request_duration_seconds";
+const GRAPHQL_ENDPOINT_SYNCED: &str = "mina_mesh_graphql_endpoint_synced";
+const DB_POOL_CONNECTIONS: &str = "mina_mesh_db_pool_connections";
+const DB_POOL_IDLE_CONNECTIONS: &str = "mina_mesh_db_pool_idle_connections";
+const CACHE_LOOKUPS_TOTAL: &str = "mina_mesh_cache_lookups_total";
+const CACHE_ENTRIES: &str = "mina_mesh_cache_entries";
+const CACHE_BYTES: &str = "mina_mesh_cache_bytes";
+
+const LATENCY_BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// The process-wide Prometheus recorder. It is installed on first use, so that
+/// several routers (as in tests) share a single registry. If the embedding
+/// application already installed a recorder, `/metrics` renders an empty
+/// registry instead of failing.
+pub fn prometheus_handle() -> &'static PrometheusHandle {
+  static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+  HANDLE.get_or_init(|| {
+    let recorder = PrometheusBuilder::new()
+      .set_buckets_for_metric(Matcher::Suffix("_duration_seconds".to_string()), &LATENCY_BUCKETS)
+      .expect("Latency buckets must not be empty")
+      .build_recorder();
+    let handle = recorder.handle();
+    if let Err(e) = metrics::set_global_recorder(recorder) {
+      tracing::warn!("Metrics are not exported, another recorder is installed: {}", e);
+    }
+    handle
+  })
+}
+
+/// Renders all metrics in the Prometheus text exposition format, sampling the
+/// archive pools and caches on the way.
+pub fn render(mina_mesh: &MinaMesh) -> String {
+  let pools = [("primary", Some(&mina_mesh.pg_pool)), ("replica", mina_mesh.replica_pg_pool.as_ref())];
+  for (name, pg_pool) in pools {
+    if let Some(pg_pool) = pg_pool {
+      gauge!(DB_POOL_CONNECTIONS, "pool" => name).set(pg_pool.size() as f64);
+      gauge!(DB_POOL_IDLE_CONNECTIONS, "pool" => name).set(pg_pool.num_idle() as f64);
+    }
+  }
+  for (cache, stats) in mina_mesh.cache_stats() {
+    gauge!(CACHE_ENTRIES, "cache" => cache).set(stats.entries as f64);
+    if let Some(bytes) = stats.bytes {
+      gauge!(CACHE_BYTES, "cache" => cache).set(bytes as f64);
+    }
+  }
+  prometheus_handle().render()
+}
+
+/// Middleware recording the latency and status of every request, labeled by
+/// the matched route rather than the raw path to keep cardinality bounded.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+  let route = match request.extensions().get::<MatchedPath>() {
+    Some(matched_path) => matched_path.as_str().to_string(),
+    None => "unmatched".to_string(),
+  };
+  let start = Instant::now();
+  let response = next.run(request).await;
+  let labels = [("route", route), ("status", response.status().as_u16().to_string())];
+  counter!(HTTP_REQUESTS_TOTAL, &labels).increment(1);
+  histogram!(HTTP_REQUEST_DURATION_SECONDS, &labels).record(start.elapsed().as_secs_f64());
+  response
+}
+
+pub fn record_error(error_code: i32) {
+  counter!(ERRORS_TOTAL, "code" => error_code.to_string()).increment(1);
+}
+
+/// Records a request to a GraphQL endpoint, labeled by the position of the
+/// endpoint in the configuration rather than its URL, which may hold
+/// credentials.
+pub fn record_graphql_request(endpoint: &str, start: Instant, failed: bool) {
+  let labels = [("endpoint", endpoint.to_string())];
+  counter!(GRAPHQL_REQUESTS_TOTAL, &labels).increment(1);
+  histogram!(GRAPHQL_REQUEST_DURATION_SECONDS, &labels).record(start.elapsed().as_secs_f64());
+  if failed {
+    counter!(GRAPHQL_FAILURES_TOTAL, &labels).increment(1);
+  }
+}
+
+pub fn record_graphql_endpoint_health(endpoint: &str, synced: bool) {
+  gauge!(GRAPHQL_ENDPOINT_SYNCED, "endpoint" => endpoint.to_string()).set(if synced { 1.0 } else { 0.0 });
+}
+
+pub fn record_cache_lookup(cache: &'static str, hit: bool) {
+  let result = if hit { "hit" } else { "miss" };
+  counter!(CACHE_LOOKUPS_TOTAL, "cache" => cache, "result" => result).increment(1);
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeSet;
+
+use convert_case::{Case, Casing};
+use serde_json::{json, Map, Value};
+
+use crate::{
+  create_router::{is_served, mesh_routes},
+  operation_types,
+  test::fixtures,
+  MinaMesh, MinaMeshError,
+};
+
+/// The components of the `api.json` of the Mesh specification.
+const MESH_API: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/mesh-api.json"));
+
+/// The OpenAPI document of the Mesh endpoints served in the mode of the
+/// server, with Mina's operation types and error catalogue.
+pub fn document(mina_mesh: &MinaMesh) -> Value {
+  let examples = examples();
+  let routes = mesh_routes().into_iter().filter(|route| is_served(mina_mesh, route.path)).collect::<Vec<_>>();
+
+  let mut paths = Map::new();
+  for route in &routes {
+    let mut request = json!({ "schema": { "$ref": format!("#/components/schemas/{}", route.request) } });
+    if let Some(example) = examples.get(route.path) {
+      request["example"] = example.clone();
+    }
+    paths.insert(
+      route.path.to_string(),
+      json!({
+        "post": {
+          "operationId": route.path.replace('/', " ").to_case(Case::Camel),
+          "requestBody": { "required": true, "content": { "application/json": request } },
+          "responses": {
+            "200": {
+              "description": route.response,
+              "content": {
+                "application/json": { "schema": { "$ref": format!("#/components/schemas/{}", route.response) } }
+              }
+            },
+            "default": { "$ref": "#/components/responses/Error" }
+          }
+        }
+      }),
+    );
+  }
+
+  let errors = MinaMeshError::all_errors()
+    .into_iter()
+    .map(|error| {
+      let summary = error.description();
+      (error.error_code().to_string(), json!({ "summary": summary, "value": error.to_json() }))
+    })
+    .collect::<Map<_, _>>();
+  let responses = json!({
+    "Error": {
+      "description": "Error",
+      "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" }, "examples": errors } }
+    }
+  });
+
+  // Only the schemas of the served endpoints are documented
+  let schemas = mina_schemas();
+  let mut referenced = BTreeSet::new();
+  collect_references(&Value::Object(paths.clone()), &schemas, &mut referenced);
+  collect_references(&responses, &schemas, &mut referenced);
+  let schemas = schemas.into_iter().filter(|(name, _)| referenced.contains(name)).collect::<Map<_, _>>();
+
+  json!({
+    "openapi": "3.0.3",
+    "info": {
+      "title": "MinaMesh",
+      "version": env!("CARGO_PKG_VERSION"),
+      "description": "The Mesh endpoints served by this MinaMesh server for the Mina blockchain."
+    },
+    "paths": paths,
+    "components": { "schemas": schemas, "responses": responses }
+  })
+}
+
+/// The schemas of the Mesh specification, with Mina's operation types and
+/// the Mina-specific fields of `/search/transactions`.
+fn mina_schemas() -> Map<String, Value> {
+  let mut api: Value = serde_json::from_str(MESH_API).expect("The Mesh specification is valid JSON");
+  let Value::Object(mut schemas) = api["components"]["schemas"].take() else {
+    unreachable!("The Mesh specification has component schemas");
+  };
+  schemas.insert(
+    "OperationType".to_string(),
+    json!({
+      "type": "string",
+      "description": "The operation types of Mina, as listed by `/network/options`.",
+      "enum": operation_types(),
+    }),
+  );
+  schemas["Operation"]["properties"]["type"] = json!({ "$ref": "#/components/schemas/OperationType" });
+  schemas.insert(
+    "SearchTransactionsMetadata".to_string(),
+    json!({
+      "type": "object",
+      "description": "Mina-specific filters, which bound the results to a range of block heights and of block timestamps, in milliseconds since the epoch. Bounds are inclusive.",
+      "properties": {
+        "min_block": { "type": "integer", "format": "int64" },
+        "min_timestamp": { "type": "integer", "format": "int64" },
+        "max_timestamp": { "type": "integer", "format": "int64" },
+        "order": {
+          "type": "string",
+          "enum": ["asc", "desc"],
+          "description": "Sorts the results by block height and sequence numbers. Sorted results are paged by cursor, not by offset."
+        }
+      }
+    }),
+  );
+  let request = &mut schemas["SearchTransactionsRequest"]["properties"];
+  request["cursor"] = json!({
+    "type": "string",
+    "description": "The `next_cursor` of the previous page, or an empty string for the first page."
+  });
+  request["metadata"] = json!({ "$ref": "#/components/schemas/SearchTransactionsMetadata" });
+  schemas["SearchTransactionsResponse"]["properties"]["next_cursor"] = json!({
+    "type": "string",
+    "description": "The cursor after the last result of a page of a search paged by cursor. A walk is caught up when a page is empty."
+  });
+  schemas
+}
+
+/// Adds the names of the schemas the value references, transitively.
+fn collect_references(value: &Value, schemas: &Map<String, Value>, referenced: &mut BTreeSet<String>) {
+  match value {
+    Value::Object(object) => {
+      let name = object
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/components/schemas/"));
+      if let Some(name) = name {
+        if referenced.insert(name.to_string()) {
+          if let Some(schema) = schemas.get(name) {
+            collect_references(schema, schemas, referenced);
+          }
+        }
+      }
+      object.values().for_each(|value| collect_references(value, schemas, referenced));
+    }
+    Value::Array(values) => values.iter().for_each(|value| collect_references(value, schemas, referenced)),
+    _ => {}
+  }
+}
+
+/// An example request per endpoint, the first of its request fixtures.
+fn examples() -> Map<String, Value> {
+  let mut examples = Map::new();
+  for (path, requests) in fixtures::all() {
+    if let (false, Some(request)) = (examples.contains_key(path), requests.first()) {
+      examples.insert(path.to_string(), serde_json::to_value(request).unwrap_or_default());
+    }
+  }
+  examples
+}
@@ -44,10 +44,7 @@ pub async fn handle_playground() -> impl IntoResponse {
     scalar_js = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/scalar.standalone.min.js")),
     scalar_css = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/static/rust-theme.css")),
     title = "MinaMesh Playground",
-    spec_url = OPENAPI_SPEC
+    spec_url = "/openapi.json"
   );
   Html(html)
 }
-
-static OPENAPI_SPEC: &str =
-  "https://raw.githubusercontent.com/coinbase/mesh-specifications/7f9f2f691f1ab1f7450e376d031e60d997dacbde/api.json";
@@ -145,6 +145,13 @@ impl ROInput {
     self
   }
 
+  /// Convert into the `mina_hasher` random oracle input consumed by
+  /// `mina_signer`
+  pub fn to_hasher_roinput(&self) -> mina_hasher::ROInput {
+    let roi = self.fields.iter().fold(mina_hasher::ROInput::new(), |roi, f| roi.append_field(*f));
+    self.bits.iter().by_vals().fold(roi, |roi, b| roi.append_bool(b))
+  }
+
   /// Serialize random oracle input to bytes
   pub fn to_bytes(&self) -> Vec<u8> {
     let mut bits: BitVec<u8> = self.fields.iter().fold(BitVec::new(), |mut acc, fe| {
@@ -1,8 +1,9 @@
-use mina_signer::{BaseField, CompressedPubKey, Signature};
+use coinbase_mesh::models::NetworkIdentifier;
+use mina_signer::{BaseField, CompressedPubKey, NetworkId, PubKey, Signature, Signer};
 use o1_utils::FieldHelpers;
 use sha2::Digest;
 
-use crate::MinaMeshError;
+use crate::{MinaMeshError, ROInput};
 
 /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/lib/base58_check/base58_check.ml
 ///
@@ -50,6 +51,14 @@ pub fn validate_base58_with_checksum(input: &str, expected_version: Option<u8>)
   Ok(())
 }
 
+/// Encodes a payload as base58 with a version byte and a double-SHA256
+/// checksum, the inverse of `validate_base58_with_checksum`.
+pub fn encode_base58_with_checksum(version: u8, payload: &[u8]) -> String {
+  let versioned = [&[version], payload].concat();
+  let checksum = sha2::Sha256::digest(sha2::Sha256::digest(&versioned));
+  bs58::encode([versioned.as_slice(), &checksum[..4]].concat()).with_alphabet(bs58::Alphabet::BITCOIN).into_string()
+}
+
 /// Validates a base58-encoded string.
 ///
 /// Less strict than `validate_base58_with_checksum`.
@@ -123,6 +132,69 @@ pub fn decode_signature(signature_raw: &str) -> Result<Signature, MinaMeshError>
   Ok(Signature::new(rx, s))
 }
 
+/// Converts a hex-encoded signature into the versioned base58 form the daemon
+/// accepts in zkApp command authorizations.
+/// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/lib/base58_check/version_bytes.ml#L45
+pub fn signature_to_base58(signature_raw: &str) -> Result<String, MinaMeshError> {
+  decode_signature(signature_raw)?;
+  let bytes = hex::decode(signature_raw).map_err(|e| MinaMeshError::SignatureInvalid(e.to_string()))?;
+  Ok(encode_base58_with_checksum(SIGNATURE_VERSION_BYTE, &[&[SIGNATURE_VERSION_NUMBER], bytes.as_slice()].concat()))
+}
+
+const SIGNATURE_VERSION_BYTE: u8 = 0x9A;
+const SIGNATURE_VERSION_NUMBER: u8 = 0x01;
+
+/// Selects the signature domain for the network: only mainnet signs with the
+/// mainnet prefix, every other network uses the testnet one.
+pub fn signer_network_id(network_identifier: &NetworkIdentifier) -> NetworkId {
+  match network_identifier.network.as_str() {
+    "mainnet" => NetworkId::MAINNET,
+    _ => NetworkId::TESTNET,
+  }
+}
+
+/// Verifies a legacy Schnorr-Poseidon signature over a user command's random
+/// oracle input.
+/// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/lib/signature_lib/schnorr.ml#L188
+pub fn verify_signature(signature: &Signature, pub_key: &PubKey, input: &ROInput, network_id: NetworkId) -> bool {
+  mina_signer::create_legacy::<SignedPayload>(network_id).verify(signature, pub_key, &SignedPayload(input.clone()))
+}
+
+/// Verifies a Schnorr-Poseidon signature over a zkApp command's full
+/// commitment, as produced for the fee payer and signature-authorized account
+/// updates.
+/// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/lib/mina_base/zkapp_command.ml#L1765
+pub fn verify_zkapp_signature(
+  signature: &Signature,
+  pub_key: &PubKey,
+  full_commitment: BaseField,
+  network_id: NetworkId,
+) -> bool {
+  let input = ROInput::new().append_field(full_commitment);
+  mina_signer::create_kimchi::<SignedPayload>(network_id).verify(signature, pub_key, &SignedPayload(input))
+}
+
+/// Random oracle input wrapped for hashing by `mina_signer`.
+#[derive(Clone)]
+struct SignedPayload(ROInput);
+
+impl mina_hasher::Hashable for SignedPayload {
+  type D = NetworkId;
+
+  fn to_roinput(&self) -> mina_hasher::ROInput {
+    self.0.to_hasher_roinput()
+  }
+
+  fn domain_string(network_id: NetworkId) -> Option<String> {
+    match network_id {
+      NetworkId::MAINNET => "MinaSignatureMainnet",
+      NetworkId::TESTNET => "CodaSignature",
+    }
+    .to_string()
+    .into()
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use hex;
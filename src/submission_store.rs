@@ -0,0 +1,263 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fmt::Debug,
+  fs::{self, File, OpenOptions},
+  io::{self, Read, Seek, SeekFrom, Write},
+  os::{fd::AsRawFd, unix::fs::MetadataExt},
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use crate::{cache::LookupCounters, Cache, CacheConfig, CacheStats, LruTtlCache, MinaMeshError};
+
+/// Remembers the transactions submitted via `/construction/submit`, keyed by
+/// their hash as computed by `/construction/hash`, so that a resubmission
+/// returns the original transaction identifier instead of reaching the daemon.
+///
+/// A submission first reserves its hash, so that a concurrent submission of
+/// the same transaction is not broadcast twice, then either inserts the
+/// transaction identifier once the daemon accepted it or releases the hash.
+/// Implementations may block on I/O, so they are called off the async runtime.
+pub trait SubmissionStore: Debug + Send + Sync {
+  /// The transaction identifier the daemon returned for the transaction with
+  /// this hash, if it was submitted before.
+  fn get(&self, hash: &str) -> Result<Option<String>, MinaMeshError>;
+  /// Reserves the hash unless the transaction was submitted before or is
+  /// being submitted.
+  fn reserve(&self, hash: &str) -> Result<Reservation, MinaMeshError>;
+  /// Records the submitted transaction, releasing its hash.
+  fn insert(&self, hash: &str, transaction_identifier: &str) -> Result<(), MinaMeshError>;
+  /// Releases a reserved hash whose transaction was not submitted.
+  fn release(&self, hash: &str);
+  fn stats(&self) -> CacheStats;
+}
+
+/// The outcome of [SubmissionStore::reserve].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reservation {
+  /// The caller now submits the transaction.
+  Reserved,
+  /// The transaction is being submitted by another request.
+  Pending,
+  /// The transaction was submitted before, with this transaction identifier.
+  Submitted(String),
+}
+
+/// Remembers the most recently submitted transactions until restart.
+#[derive(Debug)]
+pub struct MemorySubmissionStore {
+  entries: LruTtlCache<String, String>,
+  pending: Mutex<HashSet<String>>,
+}
+
+impl MemorySubmissionStore {
+  pub fn new(config: CacheConfig) -> Self {
+    Self { entries: LruTtlCache::new("transaction", config), pending: Mutex::new(HashSet::new()) }
+  }
+}
+
+impl SubmissionStore for MemorySubmissionStore {
+  fn get(&self, hash: &str) -> Result<Option<String>, MinaMeshError> {
+    Ok(self.entries.get(&hash.to_string()))
+  }
+
+  fn reserve(&self, hash: &str) -> Result<Reservation, MinaMeshError> {
+    let mut pending = self.pending.lock().expect("Submission store lock poisoned");
+    if let Some(transaction_identifier) = self.entries.get(&hash.to_string()) {
+      return Ok(Reservation::Submitted(transaction_identifier));
+    }
+    Ok(if pending.insert(hash.to_string()) { Reservation::Reserved } else { Reservation::Pending })
+  }
+
+  fn insert(&self, hash: &str, transaction_identifier: &str) -> Result<(), MinaMeshError> {
+    let mut pending = self.pending.lock().expect("Submission store lock poisoned");
+    self.entries.insert(hash.to_string(), transaction_identifier.to_string());
+    pending.remove(hash);
+    Ok(())
+  }
+
+  fn release(&self, hash: &str) {
+    self.pending.lock().expect("Submission store lock poisoned").remove(hash);
+  }
+
+  fn stats(&self) -> CacheStats {
+    self.entries.stats()
+  }
+}
+
+#[derive(Debug, Default)]
+struct FileEntries {
+  entries: HashMap<String, String>,
+  // The locked files of the hashes reserved by this server
+  pending: HashMap<String, File>,
+  // The length of the file read so far
+  offset: u64,
+}
+
+/// Records submitted transactions in an append-only file, one
+/// `<hash> <transaction identifier>` line each. The guard survives restarts,
+/// and is shared by servers with access to the same file: a lookup that misses
+/// first reads the lines appended since the last one, if the file grew.
+/// A reservation holds an advisory lock on a file named after the hash, in the
+/// `.pending` directory next to the file, which the system releases should the
+/// server exit. Appends hold an advisory lock on the file itself. Entries are
+/// never evicted.
+#[derive(Debug)]
+pub struct FileSubmissionStore {
+  path: PathBuf,
+  entries: Mutex<FileEntries>,
+  lookups: LookupCounters,
+}
+
+impl FileSubmissionStore {
+  pub fn open(path: impl AsRef<Path>) -> Result<Self, MinaMeshError> {
+    let store = Self {
+      path: path.as_ref().to_path_buf(),
+      entries: Mutex::new(FileEntries::default()),
+      lookups: LookupCounters::new("transaction"),
+    };
+    store.refresh(&mut store.entries.lock().expect("Submission store lock poisoned"))?;
+    Ok(store)
+  }
+
+  fn refresh(&self, entries: &mut FileEntries) -> Result<(), MinaMeshError> {
+    let mut file =
+      OpenOptions::new().create(true).append(true).read(true).open(&self.path).map_err(|e| self.error(e))?;
+    if file.metadata().map_err(|e| self.error(e))?.len() <= entries.offset {
+      return Ok(());
+    }
+    file.seek(SeekFrom::Start(entries.offset)).map_err(|e| self.error(e))?;
+    let mut appended = String::new();
+    file.read_to_string(&mut appended).map_err(|e| self.error(e))?;
+    // A line being written by another server is read once it is complete
+    let complete = appended.rfind('\n').map_or(0, |end| end + 1);
+    for line in appended[..complete].lines() {
+      if let Some((hash, transaction_identifier)) = line.split_once(' ') {
+        entries.entries.insert(hash.to_string(), transaction_identifier.to_string());
+      }
+    }
+    entries.offset += complete as u64;
+    Ok(())
+  }
+
+  fn pending_directory(&self) -> PathBuf {
+    let mut directory = self.path.clone().into_os_string();
+    directory.push(".pending");
+    directory.into()
+  }
+
+  /// Locks the reservation file of the hash, unless another server holds it.
+  fn lock_pending(&self, hash: &str) -> Result<Option<File>, MinaMeshError> {
+    let directory = self.pending_directory();
+    fs::create_dir_all(&directory).map_err(|e| self.error(e))?;
+    let path = directory.join(hash);
+    loop {
+      let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path).map_err(|e| self.error(e))?;
+      if !flock(&file, libc::LOCK_EX | libc::LOCK_NB).map_err(|e| self.error(e))? {
+        return Ok(None);
+      }
+      // The previous holder removes the file before releasing it, so the lock
+      // only counts if the file is still in place
+      let locked = file.metadata().map_err(|e| self.error(e))?;
+      if fs::metadata(&path).is_ok_and(|current| current.dev() == locked.dev() && current.ino() == locked.ino()) {
+        return Ok(Some(file));
+      }
+    }
+  }
+
+  // The file is removed before the lock is released by closing it
+  fn unlock_pending(&self, entries: &mut FileEntries, hash: &str) {
+    if let Some(file) = entries.pending.remove(hash) {
+      if let Err(e) = fs::remove_file(self.pending_directory().join(hash)) {
+        tracing::warn!("Failed to remove the reservation of {}: {}", hash, e);
+      }
+      drop(file);
+    }
+  }
+
+  fn lookup(&self, entries: &mut FileEntries, hash: &str) -> Result<Option<String>, MinaMeshError> {
+    if !entries.entries.contains_key(hash) {
+      self.refresh(entries)?;
+    }
+    let transaction_identifier = entries.entries.get(hash).cloned();
+    self.lookups.record(transaction_identifier.is_some());
+    Ok(transaction_identifier)
+  }
+
+  fn error(&self, e: std::io::Error) -> MinaMeshError {
+    MinaMeshError::Exception(format!("Submission store {}: {}", self.path.display(), e))
+  }
+}
+
+impl SubmissionStore for FileSubmissionStore {
+  fn get(&self, hash: &str) -> Result<Option<String>, MinaMeshError> {
+    let mut entries = self.entries.lock().expect("Submission store lock poisoned");
+    self.lookup(&mut entries, hash)
+  }
+
+  fn reserve(&self, hash: &str) -> Result<Reservation, MinaMeshError> {
+    let mut entries = self.entries.lock().expect("Submission store lock poisoned");
+    if let Some(transaction_identifier) = self.lookup(&mut entries, hash)? {
+      return Ok(Reservation::Submitted(transaction_identifier));
+    }
+    if entries.pending.contains_key(hash) {
+      return Ok(Reservation::Pending);
+    }
+    let Some(file) = self.lock_pending(hash)? else {
+      return Ok(Reservation::Pending);
+    };
+    entries.pending.insert(hash.to_string(), file);
+    // Another server may have submitted the transaction since the lookup
+    self.refresh(&mut entries)?;
+    if let Some(transaction_identifier) = entries.entries.get(hash).cloned() {
+      self.unlock_pending(&mut entries, hash);
+      return Ok(Reservation::Submitted(transaction_identifier));
+    }
+    Ok(Reservation::Reserved)
+  }
+
+  // The line is picked up by the next refresh, along with those of other
+  // servers, so the offset is left alone.
+  fn insert(&self, hash: &str, transaction_identifier: &str) -> Result<(), MinaMeshError> {
+    let mut entries = self.entries.lock().expect("Submission store lock poisoned");
+    let mut file = OpenOptions::new().create(true).append(true).open(&self.path).map_err(|e| self.error(e))?;
+    flock(&file, libc::LOCK_EX).map_err(|e| self.error(e))?;
+    file.write_all(format!("{} {}\n", hash, transaction_identifier).as_bytes()).map_err(|e| self.error(e))?;
+    entries.entries.insert(hash.to_string(), transaction_identifier.to_string());
+    self.unlock_pending(&mut entries, hash);
+    Ok(())
+  }
+
+  fn release(&self, hash: &str) {
+    let mut entries = self.entries.lock().expect("Submission store lock poisoned");
+    self.unlock_pending(&mut entries, hash);
+  }
+
+  fn stats(&self) -> CacheStats {
+    let entries = self.entries.lock().expect("Submission store lock poisoned");
+    CacheStats {
+      hits: self.lookups.hits(),
+      misses: self.lookups.misses(),
+      entries: entries.entries.len(),
+      capacity: usize::MAX,
+      bytes: Some(entries.offset as usize),
+    }
+  }
+}
+
+/// Takes an advisory lock on the file, held until it is closed. Returns
+/// whether it was taken, which is always the case unless `LOCK_NB` is set.
+fn flock(file: &File, operation: libc::c_int) -> io::Result<bool> {
+  loop {
+    // SAFETY: the descriptor belongs to the open file for the whole call
+    if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+      return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    match e.kind() {
+      io::ErrorKind::Interrupted => continue,
+      io::ErrorKind::WouldBlock => return Ok(false),
+      _ => return Err(e),
+    }
+  }
+}
@@ -1,3 +1,5 @@
+pub mod fixtures;
+
 use std::fmt::Display;
 
 use anyhow::Result;
@@ -5,7 +7,8 @@ use axum::{
   body::{to_bytes, Body},
   http::{Request, StatusCode},
   response::IntoResponse,
-  Router,
+  routing::post,
+  Json, Router,
 };
 use coinbase_mesh::models::{
   AccountIdentifier, Amount, Currency, CurveType, NetworkIdentifier, NetworkRequest, Operation, OperationIdentifier,
@@ -14,9 +17,10 @@ use coinbase_mesh::models::{
 use pretty_assertions::assert_eq;
 use reqwest::Client;
 use serde_json::{json, Map, Value};
+use tokio::net::TcpListener;
 use tower::ServiceExt;
 
-use crate::{create_router, MinaMesh, OperationType::*};
+use crate::{create_router, util::DEFAULT_TOKEN_ID, MinaMesh, MinaMeshConfig, OperationType::*};
 
 pub struct ResponseComparisonContext {
   pub router: Router,
@@ -193,6 +197,40 @@ pub fn network_request() -> NetworkRequest {
   NetworkRequest::new(network_id())
 }
 
+/// Serves a fake Mina GraphQL on a local port and returns its URL. The genesis
+/// block query of startup is answered, any other query gets `data`.
+pub async fn mock_daemon(data: Value) -> Result<String> {
+  let handler = move |Json(body): Json<Value>| async move {
+    let query = body["query"].as_str().unwrap_or_default();
+    Json(if query.contains("genesisBlock") {
+      json!({ "data": { "genesisBlock": {
+        // cspell:disable-next-line
+        "stateHash": "3NKeMoncuHab5ScarV5ViyF16cJPT4taWNSaTLS64Dp67wuXigPZ",
+        "protocolState": { "consensusState": { "blockHeight": "1" } }
+      } } })
+    } else {
+      json!({ "data": data })
+    })
+  };
+  let listener = TcpListener::bind("127.0.0.1:0").await?;
+  let url = format!("http://{}/graphql", listener.local_addr()?);
+  tokio::spawn(async move { axum::serve(listener, Router::new().route("/graphql", post(handler))).await });
+  Ok(url)
+}
+
+/// The configuration of a devnet server in tests, with a small pool and the
+/// block and search count caches disabled so that every request hits the
+/// archive.
+pub fn config() -> MinaMeshConfig {
+  MinaMeshConfig {
+    block_cache_size: 0,
+    search_count_cache_ttl: 0,
+    max_db_pool_size: 10,
+    network_id: Some(format!("{}:{}", DEVNET_BLOCKCHAIN_ID, DEVNET_NETWORK_ID)),
+    ..Default::default()
+  }
+}
+
 pub fn payment_operations(
   (fee_act, fee_amt): (&str, &str),
   (sender_act, sender_amt): (&str, &str),
@@ -281,6 +319,50 @@ pub fn delegation_operations(
   ]
 }
 
+pub fn zkapp_operations(
+  (fee_payer_act, fee_amt): (&str, &str),
+  balance_updates: &[(&str, &str)],
+  token_id: &str,
+) -> Vec<Operation> {
+  let mut operations = vec![Operation {
+    operation_identifier: OperationIdentifier::new(0).into(),
+    related_operations: None,
+    r#type: ZkappFeePayerDec.to_string(),
+    account: Some(
+      AccountIdentifier {
+        address: fee_payer_act.into(),
+        sub_account: None,
+        metadata: json!({ "token_id": DEFAULT_TOKEN_ID }).into(),
+      }
+      .into(),
+    ),
+    amount: Some(Box::new(Amount::new(fee_amt.into(), Currency::new("MINA".into(), 9)))),
+    coin_change: None,
+    metadata: None,
+    status: None,
+  }];
+  for (act, amt) in balance_updates {
+    operations.push(Operation {
+      operation_identifier: OperationIdentifier::new(operations.len() as i64).into(),
+      related_operations: None,
+      r#type: ZkappBalanceUpdate.to_string(),
+      account: Some(
+        AccountIdentifier {
+          address: (*act).into(),
+          sub_account: None,
+          metadata: json!({ "token_id": token_id }).into(),
+        }
+        .into(),
+      ),
+      amount: Some(Box::new(Amount::new((*amt).into(), Currency::new("MINA".into(), 9)))),
+      coin_change: None,
+      metadata: None,
+      status: None,
+    });
+  }
+  operations
+}
+
 //cspell:disable
 pub fn unsigned_transaction_payment() -> String {
   r#"{
@@ -1,4 +1,4 @@
-use mina_mesh::{
+use crate::{
   models::{BlockRequest, PartialBlockIdentifier},
   test::network_id,
 };
@@ -1,4 +1,4 @@
-use mina_mesh::{
+use crate::{
   models::{ConstructionCombineRequest, SignatureType},
   test::{network_id, signature, unsigned_transaction_delegation, unsigned_transaction_payment},
 };
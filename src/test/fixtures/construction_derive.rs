@@ -1,4 +1,4 @@
-use mina_mesh::{
+use crate::{
   models::{ConstructionDeriveRequest, CurveType::Tweedle, PublicKey},
   test::network_id,
 };
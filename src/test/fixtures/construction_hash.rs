@@ -1,4 +1,4 @@
-use mina_mesh::{
+use crate::{
   models::ConstructionHashRequest,
   test::{network_id, signed_transaction_delegation, signed_transaction_payment},
 };
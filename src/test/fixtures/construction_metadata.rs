@@ -1,4 +1,4 @@
-use mina_mesh::{models::ConstructionMetadataRequest, test::network_id};
+use crate::{models::ConstructionMetadataRequest, test::network_id};
 
 use super::CompareGroup;
 
@@ -1,4 +1,4 @@
-use mina_mesh::{
+use crate::{
   models::ConstructionPayloadsRequest,
   test::{delegation_operations, network_id, payment_operations},
   TransactionMetadata,
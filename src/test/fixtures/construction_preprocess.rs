@@ -1,4 +1,4 @@
-use mina_mesh::{
+use crate::{
   models::ConstructionPreprocessRequest,
   test::{delegation_operations, network_id, payment_operations},
   PreprocessMetadata,
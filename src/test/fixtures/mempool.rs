@@ -1,11 +1,10 @@
-use mina_mesh::{
+use crate::{
   models::{MempoolTransactionRequest, NetworkRequest, TransactionIdentifier},
   test::network_id,
 };
 
 use super::CompareGroup;
 
-#[allow(dead_code)]
 pub fn mempool<'a>() -> CompareGroup<'a> {
   ("/mempool", vec![Box::new(NetworkRequest::new(network_id()))])
 }
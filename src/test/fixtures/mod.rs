@@ -27,3 +27,27 @@ pub use network::*;
 pub use search_transactions::*;
 
 pub type CompareGroup<'a> = (&'a str, Vec<Box<dyn ErasedSerialize>>);
+
+/// The request fixtures of every endpoint, of which the first request of an
+/// endpoint documents it in `/openapi.json`.
+pub fn all<'a>() -> Vec<CompareGroup<'a>> {
+  vec![
+    account_balance(),
+    account_balance_not_exists(),
+    block(),
+    block_not_found(),
+    construction_combine(),
+    construction_derive(),
+    construction_hash(),
+    construction_metadata(),
+    construction_parse(),
+    construction_payloads(),
+    construction_preprocess(),
+    mempool(),
+    mempool_transaction(),
+    network_list(),
+    network_options(),
+    network_status(),
+    search_transactions(),
+  ]
+}
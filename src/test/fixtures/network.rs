@@ -1,6 +1,7 @@
-use mina_mesh::test::network_request;
 use serde::{ser::SerializeStruct, Serialize, Serializer};
 
+use crate::test::network_request;
+
 use super::CompareGroup;
 
 struct EmptyPayload;
@@ -1,4 +1,4 @@
-use mina_mesh::{
+use crate::{
   models::{SearchTransactionsRequest, TransactionIdentifier},
   test::network_id,
 };
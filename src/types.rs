@@ -1,7 +1,7 @@
-use std::fmt;
+use std::{cmp::Ordering, time::Instant};
 
 use bitvec::prelude::*;
-use coinbase_mesh::models::Operation;
+use coinbase_mesh::models::{Operation, SearchTransactionsRequest, SearchTransactionsResponse};
 use derive_more::derive::Display;
 use mina_signer::CompressedPubKey;
 use serde::{Deserialize, Serialize};
@@ -14,11 +14,11 @@ use strum_macros::{Display as StrumDisplay, EnumIter, EnumString};
 use crate::{
   memo::Memo,
   signer_utils::{address_to_compressed_pub_key, validate_base58},
-  util::DEFAULT_TOKEN_ID,
+  util::{is_default_token, DEFAULT_TOKEN_ID},
   MinaMeshError,
   OperationType::*,
   PartialReason::{self, *},
-  ROInput,
+  ROInput, ZkappCommandPayload,
 };
 
 #[derive(Type, Debug, PartialEq, Eq, Serialize)]
@@ -406,17 +406,7 @@ impl InternalCommandOperationsData for InternalCommandMetadata {
 
 #[derive(Debug, Hash, PartialEq, Eq)]
 pub enum CacheKey {
-  NetworkId,           // Network
-  Transaction(String), // Caching submitted transactions
-}
-
-impl fmt::Display for CacheKey {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    match self {
-      CacheKey::NetworkId => write!(f, "network_id"),
-      CacheKey::Transaction(signed_tx_str) => write!(f, "txn_{}", signed_tx_str),
-    }
-  }
+  NetworkId, // Network
 }
 
 // Construction types
@@ -666,18 +656,25 @@ pub struct TransactionUnsigned {
   pub payment: Option<Payment>,
   #[serde(rename = "stakeDelegation")]
   pub stake_delegation: Option<StakeDelegation>,
+  #[serde(rename = "zkappCommand", default, skip_serializing_if = "Option::is_none")]
+  pub zkapp_command: Option<ZkappCommandPayload>,
 }
 
+/// For zkApp commands `signature` holds the fee payer's signature, the account
+/// update signatures are carried by the command itself.
 #[derive(Serialize, Deserialize)]
 pub struct TransactionSigned {
   pub signature: String,
   pub payment: Option<Payment>,
   pub stake_delegation: Option<StakeDelegation>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub zkapp_command: Option<ZkappCommandPayload>,
 }
 
 pub trait HasPaymentAndDelegation {
   fn payment(&self) -> Option<&Payment>;
   fn stake_delegation(&self) -> Option<&StakeDelegation>;
+  fn zkapp_command(&self) -> Option<&ZkappCommandPayload>;
 }
 
 impl HasPaymentAndDelegation for TransactionSigned {
@@ -688,6 +685,10 @@ impl HasPaymentAndDelegation for TransactionSigned {
   fn stake_delegation(&self) -> Option<&StakeDelegation> {
     self.stake_delegation.as_ref()
   }
+
+  fn zkapp_command(&self) -> Option<&ZkappCommandPayload> {
+    self.zkapp_command.as_ref()
+  }
 }
 
 impl HasPaymentAndDelegation for TransactionUnsigned {
@@ -698,6 +699,10 @@ impl HasPaymentAndDelegation for TransactionUnsigned {
   fn stake_delegation(&self) -> Option<&StakeDelegation> {
     self.stake_delegation.as_ref()
   }
+
+  fn zkapp_command(&self) -> Option<&ZkappCommandPayload> {
+    self.zkapp_command.as_ref()
+  }
 }
 
 impl TransactionSigned {
@@ -716,8 +721,10 @@ impl TransactionSigned {
       Ok(payment.from.clone())
     } else if let Some(stake_delegation) = &self.stake_delegation {
       Ok(stake_delegation.delegator.clone())
+    } else if let Some(zkapp_command) = &self.zkapp_command {
+      Ok(zkapp_command.fee_payer.public_key.clone())
     } else {
-      Err(MinaMeshError::Exception("No payment or delegation found".to_string()))
+      Err(MinaMeshError::Exception("No payment, delegation or zkApp command found".to_string()))
     }
   }
 }
@@ -743,6 +750,7 @@ impl From<&UserCommandPayload> for TransactionUnsigned {
           valid_until: cmd.valid_until,
         }),
         stake_delegation: None,
+        zkapp_command: None,
       },
       UserCommandBody::Delegation { new_delegate } => TransactionUnsigned {
         random_oracle_input: roi_hex,
@@ -756,11 +764,26 @@ impl From<&UserCommandPayload> for TransactionUnsigned {
           memo: Some(cmd.memo.as_string()),
           valid_until: cmd.valid_until,
         }),
+        zkapp_command: None,
       },
     }
   }
 }
 
+impl From<&ZkappCommandPayload> for TransactionUnsigned {
+  /// zkApp commands are signed over their full commitment, which is handed out
+  /// in the signing payloads, so there is no random oracle input to carry.
+  fn from(cmd: &ZkappCommandPayload) -> Self {
+    TransactionUnsigned {
+      random_oracle_input: String::new(),
+      signer_input: SignerInput { prefix: vec![], suffix: vec![] },
+      payment: None,
+      stake_delegation: None,
+      zkapp_command: Some(cmd.clone()),
+    }
+  }
+}
+
 impl TransactionUnsigned {
   pub fn as_json_string(&self) -> Result<String, MinaMeshError> {
     serde_json::to_string(self)
@@ -1102,6 +1125,14 @@ impl PartialUserCommand {
       errors.push(PartialReason::IncorrectTokenId);
     });
 
+    // Payments only move MINA, custom tokens are transferred by zkApp commands
+    if validate_base58(&token).is_ok() && !is_default_token(&token) {
+      errors.push(PartialReason::InvalidMetadata(format!(
+        "Token {} can only be transferred with zkapp_balance_update operations",
+        token
+      )));
+    }
+
     if fee_payment.account != source_dec.account {
       errors.push(PartialReason::FeePayerAndSourceMismatch);
     }
@@ -1222,7 +1253,7 @@ impl PartialUserCommand {
       .ok_or(PartialReason::CanNotFindKind(op_type.to_string()))
   }
 
-  fn parse_amount_as_i64(operation: &Operation) -> Result<i64, PartialReason> {
+  pub(crate) fn parse_amount_as_i64(operation: &Operation) -> Result<i64, PartialReason> {
     operation
       .amount
       .as_ref()
@@ -1230,7 +1261,7 @@ impl PartialUserCommand {
       .and_then(|amount| amount.value.parse::<i64>().map_err(|_| PartialReason::AmountNotValid))
   }
 
-  fn token_id_from_operation(operation: &Operation) -> String {
+  pub(crate) fn token_id_from_operation(operation: &Operation) -> String {
     operation
       .account
       .as_ref()
@@ -1240,7 +1271,7 @@ impl PartialUserCommand {
       .to_string()
   }
 
-  fn address_from_operation(operation: &Operation) -> String {
+  pub(crate) fn address_from_operation(operation: &Operation) -> String {
     if operation.r#type == DelegateChange.to_string() {
       operation
         .metadata
@@ -1263,6 +1294,7 @@ pub struct TransactionMetadata {
   pub account_creation_fee: Option<String>,
   pub valid_until: Option<String>,
   pub memo: Option<String>,
+  pub token_owner: Option<String>,
 }
 
 impl TransactionMetadata {
@@ -1283,9 +1315,15 @@ impl TransactionMetadata {
       account_creation_fee: account_creation_fee.map(Into::into),
       valid_until: valid_until.map(Into::into),
       memo: memo.map(Into::into),
+      token_owner: None,
     }
   }
 
+  pub fn with_token_owner(mut self, token_owner: Option<impl Into<String>>) -> Self {
+    self.token_owner = token_owner.map(Into::into);
+    self
+  }
+
   /// Convert metadata into JSON for Rosetta responses
   pub fn to_json(&self) -> Value {
     let mut map = serde_json::Map::new();
@@ -1306,6 +1344,10 @@ impl TransactionMetadata {
       map.insert("account_creation_fee".to_string(), json!(account_creation_fee));
     }
 
+    if let Some(token_owner) = &self.token_owner {
+      map.insert("token_owner".to_string(), json!(token_owner));
+    }
+
     json!(map)
   }
 }
@@ -1344,6 +1386,239 @@ impl TryFrom<Value> for TransactionMetadata {
 
     let memo = value.get("memo").and_then(Value::as_str).map(|s| s.to_string());
 
-    Ok(TransactionMetadata { sender, receiver, nonce, token_id, account_creation_fee, valid_until, memo })
+    let token_owner = value.get("token_owner").and_then(Value::as_str).map(|s| s.to_string());
+
+    Ok(TransactionMetadata { sender, receiver, nonce, token_id, account_creation_fee, valid_until, memo, token_owner })
+  }
+}
+
+/// Outcome of a single dependency check of `/health/ready`.
+#[derive(Debug, Serialize)]
+pub struct DependencyHealth {
+  pub healthy: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub height: Option<i64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sync_status: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+impl DependencyHealth {
+  pub fn healthy(height: i64) -> Self {
+    Self { healthy: true, height: Some(height), sync_status: None, error: None }
+  }
+
+  pub fn unhealthy(error: impl ToString) -> Self {
+    Self { healthy: false, height: None, sync_status: None, error: Some(error.to_string()) }
+  }
+}
+
+/// How far the archive lags behind the daemon's best tip, in blocks.
+#[derive(Debug, Serialize)]
+pub struct TipDrift {
+  pub healthy: bool,
+  pub drift: Option<i64>,
+  pub max_drift: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessReport {
+  pub ready: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub archive: Option<DependencyHealth>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub daemon: Option<DependencyHealth>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub tip_drift: Option<TipDrift>,
+}
+
+/// A `/search/transactions` request, paged by `offset` as in the Mesh
+/// specification or, given a `cursor`, in the global order of
+/// [SearchTransactionsCursor].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchTransactionsPageRequest {
+  #[serde(flatten)]
+  pub request: SearchTransactionsRequest,
+  /// The `next_cursor` of the previous page, or an empty string for the first
+  /// page.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub cursor: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub metadata: Option<SearchTransactionsMetadata>,
+}
+
+impl From<SearchTransactionsRequest> for SearchTransactionsPageRequest {
+  fn from(request: SearchTransactionsRequest) -> Self {
+    Self { request, cursor: None, metadata: None }
+  }
+}
+
+/// Mina-specific filters of `/search/transactions`, which bound the results to
+/// a range of block heights and of block timestamps, in milliseconds since
+/// the epoch. Bounds are inclusive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchTransactionsMetadata {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub min_block: Option<i64>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub min_timestamp: Option<i64>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub max_timestamp: Option<i64>,
+  /// Sorts the results by block height and sequence numbers. Sorted results
+  /// are paged by cursor, from the first result if none is given, and each
+  /// page of the walk must give the order of the first.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub order: Option<SortOrder>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+  /// Oldest first
+  #[default]
+  Asc,
+  /// Newest first
+  Desc,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchTransactionsPageResponse {
+  #[serde(flatten)]
+  pub response: SearchTransactionsResponse,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub next_cursor: Option<String>,
+}
+
+/// The total counts of the results of a `/search/transactions` search, by kind
+/// of command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchCounts {
+  pub user: i64,
+  pub internal: i64,
+  pub zkapp: i64,
+}
+
+impl SearchCounts {
+  pub fn total(&self) -> i64 {
+    self.user + self.internal + self.zkapp
+  }
+}
+
+/// The Archive Database pool `/search/transactions` queries, either the read
+/// replica or the primary, and the highest canonical block it held when last
+/// checked.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchPool {
+  pub replica: bool,
+  pub max_canonical_height: i64,
+  pub checked_at: Instant,
+}
+
+/// Kinds of commands, in the order in which `/block` lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CommandKind {
+  Internal,
+  User,
+  ZkApp,
+}
+
+/// The position of a command in the results of `/search/transactions`, which
+/// are ordered by block height, block, command kind, sequence numbers and
+/// command id. Walks by cursor stop at the highest canonical block, as pending
+/// blocks above it may still be added or orphaned, so that new canonical blocks
+/// only ever extend the order and a walk neither skips nor repeats commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SearchTransactionsCursor {
+  pub height: i64,
+  pub block_id: i32,
+  pub kind: CommandKind,
+  pub sequence_no: i32,
+  pub secondary_sequence_no: i32,
+  pub id: i32,
+}
+
+impl SearchTransactionsCursor {
+  /// The opaque token handed out as `next_cursor`.
+  pub fn encode(&self) -> String {
+    let position = format!(
+      "{}:{}:{}:{}:{}:{}",
+      self.height, self.block_id, self.kind as u8, self.sequence_no, self.secondary_sequence_no, self.id
+    );
+    bs58::encode(position).into_string()
+  }
+
+  pub fn decode(cursor: &str) -> Result<Self, MinaMeshError> {
+    let invalid = || MinaMeshError::InvalidCursor(format!("'{}' is not a cursor", cursor));
+    let position = bs58::decode(cursor).into_vec().ok().and_then(|bytes| String::from_utf8(bytes).ok());
+    let parts = position.as_deref().map(|position| position.split(':').collect::<Vec<_>>()).unwrap_or_default();
+    let [height, block_id, kind, sequence_no, secondary_sequence_no, id] = parts[..] else {
+      return Err(invalid());
+    };
+    let kind = match kind {
+      "0" => CommandKind::Internal,
+      "1" => CommandKind::User,
+      "2" => CommandKind::ZkApp,
+      _ => return Err(invalid()),
+    };
+    Ok(Self {
+      height: height.parse().map_err(|_| invalid())?,
+      block_id: block_id.parse().map_err(|_| invalid())?,
+      kind,
+      sequence_no: sequence_no.parse().map_err(|_| invalid())?,
+      secondary_sequence_no: secondary_sequence_no.parse().map_err(|_| invalid())?,
+      id: id.parse().map_err(|_| invalid())?,
+    })
+  }
+
+  /// The position after which the commands of the kind follow the cursor, so
+  /// that each kind can be queried separately.
+  pub fn bound(&self, kind: CommandKind) -> Self {
+    match kind.cmp(&self.kind) {
+      Ordering::Equal => *self,
+      // All commands of the kind in the block of the cursor follow it
+      Ordering::Greater => Self { kind, sequence_no: -1, secondary_sequence_no: -1, id: -1, ..*self },
+      // None of them do
+      Ordering::Less => Self { kind, sequence_no: i32::MAX, secondary_sequence_no: i32::MAX, id: i32::MAX, ..*self },
+    }
+  }
+}
+
+impl From<&UserCommand> for SearchTransactionsCursor {
+  fn from(command: &UserCommand) -> Self {
+    Self {
+      height: command.height.unwrap_or_default(),
+      block_id: command.block_id.unwrap_or_default(),
+      kind: CommandKind::User,
+      sequence_no: command.sequence_no.unwrap_or_default(),
+      secondary_sequence_no: 0,
+      id: command.id.unwrap_or_default(),
+    }
+  }
+}
+
+impl From<&InternalCommand> for SearchTransactionsCursor {
+  fn from(command: &InternalCommand) -> Self {
+    Self {
+      height: command.height.unwrap_or_default(),
+      block_id: command.block_id,
+      kind: CommandKind::Internal,
+      sequence_no: command.sequence_no,
+      secondary_sequence_no: command.secondary_sequence_no,
+      id: command.id.unwrap_or_default(),
+    }
+  }
+}
+
+impl From<&ZkAppCommand> for SearchTransactionsCursor {
+  fn from(command: &ZkAppCommand) -> Self {
+    Self {
+      height: command.height.unwrap_or_default(),
+      block_id: command.block_id.unwrap_or_default(),
+      kind: CommandKind::ZkApp,
+      sequence_no: command.sequence_no,
+      secondary_sequence_no: 0,
+      id: command.id.unwrap_or_default(),
+    }
   }
 }
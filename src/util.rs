@@ -1,9 +1,12 @@
+use std::{future::Future, time::Duration};
+
 use anyhow::Result;
 use axum::{
   extract::Json,
   response::{IntoResponse, Response},
 };
 use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
 
 use crate::MinaMeshError;
 
@@ -39,6 +42,52 @@ impl Wrapper<Option<serde_json::Value>> {
 // cspell:disable-next-line
 pub const DEFAULT_TOKEN_ID: &str = "wSHV2S4qX9jFsLjQo8r1BsMLH2ZRKsZx6EJd1sbozGPieEC4Jf";
 pub const MINIMUM_USER_COMMAND_FEE: u64 = 1_000_000;
+/// The most events `/events/blocks` returns at once, whatever the requested
+/// `limit`.
+pub const MAX_BLOCK_EVENTS_LIMIT: i64 = 1000;
+/// The most transactions `/search/transactions` returns at once, whatever the
+/// requested `limit`.
+pub const MAX_SEARCH_TRANSACTIONS_LIMIT: i64 = 1000;
+/// Token id of MINA before Berkeley, still sent by older clients.
+pub const LEGACY_DEFAULT_TOKEN_ID: &str = "1";
+
+pub fn is_default_token(token_id: &str) -> bool {
+  token_id == DEFAULT_TOKEN_ID || token_id == LEGACY_DEFAULT_TOKEN_ID
+}
+
+tokio::task_local! {
+  /// The statement timeout of the endpoint whose queries are running.
+  static STATEMENT_TIMEOUT: Option<Duration>;
+}
+
+/// Runs the Archive Database queries of an endpoint with its statement
+/// timeout. Postgres cancels each statement of an archive transaction once it
+/// exceeds the timeout, and the queries are abandoned once they exceed it
+/// together.
+pub async fn with_statement_timeout<T>(
+  timeout: Option<Duration>,
+  queries: impl Future<Output = Result<T, MinaMeshError>>,
+) -> Result<T, MinaMeshError> {
+  let queries = STATEMENT_TIMEOUT.scope(timeout, queries);
+  match timeout {
+    Some(timeout) => tokio::time::timeout(timeout, queries)
+      .await
+      .map_err(|_| MinaMeshError::SqlTimeout(format!("Archive Database queries exceeded {:?}", timeout)))?,
+    None => queries.await,
+  }
+}
+
+/// Begins a transaction on the Archive Database in which Postgres enforces the
+/// statement timeout of the running endpoint, if any.
+pub async fn begin_archive_transaction(pg_pool: &PgPool) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+  let mut transaction = pg_pool.begin().await?;
+  if let Ok(Some(timeout)) = STATEMENT_TIMEOUT.try_with(|timeout| *timeout) {
+    sqlx::query(&format!("SET LOCAL statement_timeout = {}", timeout.as_millis().max(1)))
+      .execute(&mut *transaction)
+      .await?;
+  }
+  Ok(transaction)
+}
 
 pub fn default_mina_proxy_url() -> String {
   "https://mainnet.minaprotocol.network/graphql".to_string()
@@ -0,0 +1,607 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ark_ff::{One, Zero};
+use coinbase_mesh::models::{AccountIdentifier, Operation};
+use mina_p2p_messages::v2::{MinaBaseZkappCommandTStableV1WireStableV1, TokenIdKeyHash};
+use mina_signer::{BaseField, ScalarField, Signature};
+use mina_tree::{
+  scan_state::{
+    currency::{Amount, Fee, Nonce, Sgn, Signed, Slot},
+    transaction_logic::{
+      zkapp_command::{
+        AccountPreconditions, AccountUpdate, Actions, AuthorizationKind, Body, CallForest, Control, Events, FeePayer,
+        FeePayerBody, MayUseToken, OrIgnore, Preconditions, Update, ZkAppCommand,
+      },
+      zkapp_precondition::{Account as AccountPrecondition, ZkAppPreconditions},
+      Memo as CommandMemo,
+    },
+  },
+  AccountId, TokenId,
+};
+use o1_utils::FieldHelpers;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serde_with::{serde_as, DisplayFromStr};
+
+use crate::{
+  memo::Memo,
+  operation,
+  signer_utils::{address_to_compressed_pub_key, decode_signature, signature_to_base58, validate_base58},
+  util::{is_default_token, DEFAULT_TOKEN_ID},
+  MinaMeshError,
+  OperationType::{self, *},
+  PartialReason::{self, *},
+  PartialUserCommand,
+};
+
+/// Verification key hash the daemon expects in the authorization kind of
+/// account updates which are not proof-authorized.
+const DUMMY_VERIFICATION_KEY_HASH: &str =
+  "3392518251768960475377392625298437850623664973002200885669375116181514017494";
+
+/// Whether the operations describe a zkApp command rather than a payment or a
+/// delegation.
+pub fn is_zkapp_operations(operations: &[Operation]) -> bool {
+  operations.iter().any(|op| op.r#type == ZkappFeePayerDec.to_string() || op.r#type == ZkappBalanceUpdate.to_string())
+}
+
+/// A zkApp command as carried by unsigned and signed Construction API
+/// transactions: a fee payer and a flat list of account updates moving
+/// balances. Debited accounts authorize their update with a signature over the
+/// full transaction commitment, credited accounts need no authorization.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ZkappCommandPayload {
+  pub fee_payer: ZkappFeePayer,
+  pub account_updates: Vec<ZkappAccountUpdate>,
+  pub memo: Option<String>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ZkappFeePayer {
+  pub public_key: String,
+  #[serde_as(as = "DisplayFromStr")]
+  pub fee: u64,
+  #[serde_as(as = "DisplayFromStr")]
+  pub nonce: u32,
+  #[serde_as(as = "Option<DisplayFromStr>")]
+  pub valid_until: Option<u32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub signature: Option<String>,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ZkappAccountUpdate {
+  pub public_key: String,
+  pub token_id: String,
+  #[serde_as(as = "DisplayFromStr")]
+  pub balance_change: i64,
+  /// Owner of a custom token, whose account update approves the updates on
+  /// its token.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub token_owner: Option<String>,
+  /// Set on MINA debits paying for the creation of a custom token account,
+  /// which cannot be paid implicitly out of the token balance change.
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  pub account_creation_fee: bool,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub signature: Option<String>,
+}
+
+impl ZkappAccountUpdate {
+  pub fn requires_signature(&self) -> bool {
+    self.balance_change < 0
+  }
+
+  fn is_custom_token(&self) -> bool {
+    !is_default_token(&self.token_id)
+  }
+
+  /// The update of the token owner approving its children without
+  /// authorization, which only owners whose `access` permission is `None`
+  /// accept: /construction/metadata rejects the other token owners.
+  fn token_owner_approval(token_owner: &str) -> Self {
+    ZkappAccountUpdate {
+      public_key: token_owner.to_string(),
+      token_id: DEFAULT_TOKEN_ID.to_string(),
+      balance_change: 0,
+      token_owner: None,
+      account_creation_fee: false,
+      signature: None,
+    }
+  }
+}
+
+impl ZkappCommandPayload {
+  /// Addresses which have to sign the command, fee payer first.
+  pub fn signers(&self) -> Vec<String> {
+    let mut signers = vec![self.fee_payer.public_key.clone()];
+    for account_update in self.account_updates.iter().filter(|update| update.requires_signature()) {
+      if !signers.contains(&account_update.public_key) {
+        signers.push(account_update.public_key.clone());
+      }
+    }
+    signers
+  }
+
+  /// Attaches the signature of each signer to the fee payer and to the account
+  /// updates it authorizes.
+  pub fn with_signatures(mut self, signatures: &HashMap<String, String>) -> Result<Self, MinaMeshError> {
+    let signature_of = |address: &str| {
+      signatures
+        .get(address)
+        .cloned()
+        .ok_or(MinaMeshError::SignatureInvalid(format!("Missing signature for {}", address)))
+    };
+    self.fee_payer.signature = Some(signature_of(&self.fee_payer.public_key)?);
+    for account_update in self.account_updates.iter_mut().filter(|update| update.requires_signature()) {
+      account_update.signature = Some(signature_of(&account_update.public_key)?);
+    }
+    Ok(self)
+  }
+
+  /// The command with every signature removed.
+  pub fn unsigned(&self) -> Self {
+    let mut unsigned = self.clone();
+    unsigned.fee_payer.signature = None;
+    for account_update in unsigned.account_updates.iter_mut() {
+      account_update.signature = None;
+    }
+    unsigned
+  }
+
+  /// Ensures the fee payer and every debited account carry a well-formed
+  /// signature.
+  pub fn check_signatures(&self) -> Result<(), MinaMeshError> {
+    decode_signature(self.fee_payer.signature.as_deref().ok_or(MinaMeshError::SignatureMissing)?)?;
+    for account_update in self.account_updates.iter().filter(|update| update.requires_signature()) {
+      decode_signature(account_update.signature.as_deref().ok_or(MinaMeshError::SignatureMissing)?)?;
+    }
+    Ok(())
+  }
+
+  /// Returns the transaction commitment and the full transaction commitment.
+  /// Every signer signs the full commitment.
+  /// https://github.com/MinaProtocol/mina/blob/985eda49bdfabc046ef9001d3c406e688bc7ec45/src/lib/mina_base/zkapp_command.ml#L1379
+  pub fn commitments(&self) -> Result<(BaseField, BaseField), MinaMeshError> {
+    let zkapp_command = self.to_zkapp_command()?;
+    let commitment = zkapp_command.commitment();
+    let fee_payer_hash = AccountUpdate::of_fee_payer(zkapp_command.fee_payer.clone()).digest();
+    let full_commitment = commitment.create_complete(zkapp_command.memo.hash(), fee_payer_hash);
+    Ok((commitment.0, full_commitment.0))
+  }
+
+  /// Hex encoding of the full commitment, as handed out in signing payloads.
+  pub fn signing_payload_hex(&self) -> Result<String, MinaMeshError> {
+    let (_, full_commitment) = self.commitments()?;
+    Ok(full_commitment.to_hex().to_uppercase())
+  }
+
+  pub fn hash(&self) -> Result<String, MinaMeshError> {
+    let zkapp_command = self.to_zkapp_command()?;
+    let hash = MinaBaseZkappCommandTStableV1WireStableV1::from(&zkapp_command)
+      .hash()
+      .map_err(|e| MinaMeshError::Exception(e.to_string()))?;
+    Ok(hash.to_string())
+  }
+
+  pub fn to_operations(&self) -> Vec<Operation> {
+    let mut operations = vec![operation(
+      0,
+      Some(&format!("-{}", self.fee_payer.fee)),
+      &AccountIdentifier {
+        address: self.fee_payer.public_key.clone(),
+        metadata: Some(json!({ "token_id": DEFAULT_TOKEN_ID })),
+        sub_account: None,
+      },
+      ZkappFeePayerDec,
+      None,
+      None,
+      None,
+      None,
+    )];
+    for account_update in &self.account_updates {
+      let operation_type =
+        if account_update.account_creation_fee { AccountCreationFeeViaPayment } else { ZkappBalanceUpdate };
+      let metadata = account_update.token_owner.as_ref().map(|token_owner| json!({ "token_owner": token_owner }));
+      operations.push(operation(
+        operations.len() as i64,
+        Some(&account_update.balance_change.to_string()),
+        &AccountIdentifier {
+          address: account_update.public_key.clone(),
+          metadata: Some(json!({ "token_id": account_update.token_id })),
+          sub_account: None,
+        },
+        operation_type,
+        None,
+        None,
+        metadata.as_ref(),
+        Some(&account_update.token_id),
+      ));
+    }
+    operations
+  }
+
+  /// The `ZkappCommandInput` of the daemon's `sendZkapp` mutation.
+  pub fn to_graphql_input(&self) -> Result<Value, MinaMeshError> {
+    let fee_payer_signature = self.fee_payer.signature.as_deref().ok_or(MinaMeshError::SignatureMissing)?;
+    let account_updates = self
+      .call_forest_updates()
+      .iter()
+      .map(|(account_update, call_depth)| {
+        let signature = account_update.signature.as_deref().map(signature_to_base58).transpose()?;
+        let (magnitude, sgn) = signed_magnitude(account_update.balance_change);
+        Ok(json!({
+          "body": {
+            "publicKey": account_update.public_key,
+            "tokenId": account_update.token_id,
+            "update": {
+              "appState": [null, null, null, null, null, null, null, null],
+              "delegate": null,
+              "verificationKey": null,
+              "permissions": null,
+              "zkappUri": null,
+              "tokenSymbol": null,
+              "timing": null,
+              "votingFor": null,
+            },
+            "balanceChange": {
+              "magnitude": magnitude.to_string(),
+              "sgn": if sgn == Sgn::Neg { "Negative" } else { "Positive" },
+            },
+            "incrementNonce": false,
+            "events": [],
+            "actions": [],
+            "callData": "0",
+            "callDepth": call_depth,
+            "preconditions": {
+              "network": {
+                "snarkedLedgerHash": null,
+                "blockchainLength": null,
+                "minWindowDensity": null,
+                "totalCurrency": null,
+                "globalSlotSinceGenesis": null,
+                "stakingEpochData": ignored_epoch_data(),
+                "nextEpochData": ignored_epoch_data(),
+              },
+              "account": {
+                "balance": null,
+                "nonce": null,
+                "receiptChainHash": null,
+                "delegate": null,
+                "state": [null, null, null, null, null, null, null, null],
+                "actionState": null,
+                "provedState": null,
+                "isNew": null,
+              },
+              "validWhile": null,
+            },
+            "useFullCommitment": account_update.requires_signature(),
+            "implicitAccountCreationFee": account_update.token_owner.is_none(),
+            "mayUseToken": { "parentsOwnToken": *call_depth > 0, "inheritFromParent": false },
+            "authorizationKind": {
+              "isSigned": account_update.requires_signature(),
+              "isProved": false,
+              "verificationKeyHash": DUMMY_VERIFICATION_KEY_HASH,
+            },
+          },
+          "authorization": { "proof": null, "signature": signature },
+        }))
+      })
+      .collect::<Result<Vec<_>, MinaMeshError>>()?;
+
+    Ok(json!({
+      "feePayer": {
+        "body": {
+          "publicKey": self.fee_payer.public_key,
+          "fee": self.fee_payer.fee.to_string(),
+          "validUntil": self.fee_payer.valid_until.map(|valid_until| valid_until.to_string()),
+          "nonce": self.fee_payer.nonce.to_string(),
+        },
+        "authorization": signature_to_base58(fee_payer_signature)?,
+      },
+      "accountUpdates": account_updates,
+      "memo": self.memo()?.to_base58(),
+    }))
+  }
+
+  /// Account updates in call forest order, with their call depth. Updates on a
+  /// custom token are nested under an account update of the token owner.
+  fn call_forest_updates(&self) -> Vec<(ZkappAccountUpdate, usize)> {
+    let mut updates = Vec::new();
+    let mut current_owner: Option<&str> = None;
+    for account_update in &self.account_updates {
+      match account_update.token_owner.as_deref() {
+        Some(token_owner) => {
+          if current_owner != Some(token_owner) {
+            updates.push((ZkappAccountUpdate::token_owner_approval(token_owner), 0));
+            current_owner = Some(token_owner);
+          }
+          updates.push((account_update.clone(), 1));
+        }
+        None => {
+          current_owner = None;
+          updates.push((account_update.clone(), 0));
+        }
+      }
+    }
+    updates
+  }
+
+  fn memo(&self) -> Result<Memo, MinaMeshError> {
+    Memo::from_string(self.memo.as_deref().unwrap_or_default())
+  }
+
+  fn to_zkapp_command(&self) -> Result<ZkAppCommand, MinaMeshError> {
+    let fee_payer = FeePayer {
+      body: FeePayerBody {
+        public_key: address_to_compressed_pub_key("fee_payer", &self.fee_payer.public_key)?,
+        fee: Fee::from_u64(self.fee_payer.fee),
+        valid_until: self.fee_payer.valid_until.map(Slot::from_u32),
+        nonce: Nonce::from_u32(self.fee_payer.nonce),
+      },
+      authorization: optional_signature(self.fee_payer.signature.as_deref())?,
+    };
+
+    let account_updates = self
+      .call_forest_updates()
+      .into_iter()
+      .map(|(account_update, call_depth)| {
+        let token_id = token_id_from_base58(&account_update.token_id)?;
+        if let Some(token_owner) = &account_update.token_owner {
+          let owner_pk = address_to_compressed_pub_key("token_owner", token_owner)?;
+          if AccountId::new(owner_pk, TokenId::default()).derive_token_id() != token_id {
+            return Err(MinaMeshError::OperationsNotValid(vec![IncorrectTokenId]));
+          }
+        }
+        let (magnitude, sgn) = signed_magnitude(account_update.balance_change);
+        let (authorization_kind, authorization) = if account_update.requires_signature() {
+          (AuthorizationKind::Signature, Control::Signature(optional_signature(account_update.signature.as_deref())?))
+        } else {
+          (AuthorizationKind::NoneGiven, Control::NoneGiven)
+        };
+        let account_update = AccountUpdate {
+          body: Body {
+            public_key: address_to_compressed_pub_key("account_update", &account_update.public_key)?,
+            token_id,
+            update: Update::noop(),
+            balance_change: Signed::create(Amount::from_u64(magnitude), sgn),
+            increment_nonce: false,
+            events: Events(Vec::new()),
+            actions: Actions(Vec::new()),
+            call_data: BaseField::zero(),
+            preconditions: Preconditions {
+              network: ZkAppPreconditions::accept(),
+              account: AccountPreconditions(AccountPrecondition::accept()),
+              valid_while: OrIgnore::Ignore,
+            },
+            use_full_commitment: account_update.requires_signature(),
+            implicit_account_creation_fee: account_update.token_owner.is_none(),
+            may_use_token: if call_depth > 0 { MayUseToken::ParentsOwnToken } else { MayUseToken::No },
+            authorization_kind,
+          },
+          authorization,
+        };
+        Ok((account_update, call_depth))
+      })
+      .collect::<Result<Vec<_>, MinaMeshError>>()?;
+
+    // Build the forest back to front, attaching the pending children to the
+    // token owner update that precedes them.
+    let mut forest = CallForest::new();
+    let mut children = CallForest::new();
+    for (account_update, call_depth) in account_updates.into_iter().rev() {
+      if call_depth > 0 {
+        children = children.cons(None, account_update);
+      } else {
+        let calls = (!children.is_empty()).then(|| std::mem::replace(&mut children, CallForest::new()));
+        forest = forest.cons(calls, account_update);
+      }
+    }
+
+    Ok(ZkAppCommand { fee_payer, account_updates: forest, memo: CommandMemo(self.memo()?.0) })
+  }
+}
+
+fn signed_magnitude(balance_change: i64) -> (u64, Sgn) {
+  (balance_change.unsigned_abs(), if balance_change < 0 { Sgn::Neg } else { Sgn::Pos })
+}
+
+fn ignored_epoch_data() -> Value {
+  json!({
+    "ledger": { "hash": null, "totalCurrency": null },
+    "seed": null,
+    "startCheckpoint": null,
+    "lockCheckpoint": null,
+    "epochLength": null,
+  })
+}
+
+fn token_id_from_base58(token_id: &str) -> Result<TokenId, MinaMeshError> {
+  let token_id: TokenIdKeyHash =
+    token_id.parse().map_err(|_| MinaMeshError::OperationsNotValid(vec![IncorrectTokenId]))?;
+  Ok(TokenId::from(&token_id))
+}
+
+// Commitments do not depend on the authorization, so unsigned commands are
+// hashed with a placeholder signature.
+fn optional_signature(signature: Option<&str>) -> Result<Signature, MinaMeshError> {
+  match signature {
+    Some(signature) => decode_signature(signature),
+    None => Ok(Signature::new(BaseField::one(), ScalarField::one())),
+  }
+}
+
+/// zkApp command described by Construction API operations: a single
+/// `zkapp_fee_payer_dec`, one `zkapp_balance_update` per account update and an
+/// `account_creation_fee_via_payment` per custom token account it creates.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PartialZkappCommand {
+  pub fee_payer: String,
+  pub fee: u64,
+  pub account_updates: Vec<ZkappAccountUpdate>,
+  pub valid_until: Option<String>,
+  pub memo: Option<String>,
+}
+
+impl PartialZkappCommand {
+  pub fn from_operations(
+    operations: &[Operation],
+    valid_until: Option<String>,
+    memo: Option<String>,
+  ) -> Result<Self, MinaMeshError> {
+    let mut errors = Vec::new();
+
+    let fee_payments = Self::operations_of_type(operations, ZkappFeePayerDec);
+    let balance_updates = operations
+      .iter()
+      .filter(|op| op.r#type == ZkappBalanceUpdate.to_string() || op.r#type == AccountCreationFeeViaPayment.to_string())
+      .collect::<Vec<_>>();
+    if fee_payments.len() != 1
+      || balance_updates.is_empty()
+      || fee_payments.len() + balance_updates.len() != operations.len()
+    {
+      return Err(MinaMeshError::OperationsNotValid(vec![PartialReason::LengthMismatch(format!(
+        "Expected 1 zkapp_fee_payer_dec and at least 1 zkapp_balance_update operation, got {} operations",
+        operations.len()
+      ))]));
+    }
+
+    let fee_payment = fee_payments[0];
+    if !is_default_token(&PartialUserCommand::token_id_from_operation(fee_payment)) {
+      errors.push(IncorrectTokenId);
+    }
+    let fee = PartialUserCommand::parse_amount_as_i64(fee_payment).map_err(|e| {
+      errors.push(e);
+      MinaMeshError::OperationsNotValid(errors.clone())
+    })?;
+    if fee >= 0 {
+      errors.push(FeeNotNegative);
+    }
+
+    let mut balance_by_token: BTreeMap<String, i64> = BTreeMap::new();
+    let mut account_updates = Vec::new();
+    for balance_update in balance_updates {
+      if balance_update.account.is_none() {
+        errors.push(AccountNotSome);
+        continue;
+      }
+      // The legacy MINA token id is accepted, but commands are built with the
+      // base58 one.
+      let token_id = match PartialUserCommand::token_id_from_operation(balance_update) {
+        token_id if is_default_token(&token_id) => DEFAULT_TOKEN_ID.to_string(),
+        token_id => token_id,
+      };
+      if validate_base58(&token_id).is_err() {
+        errors.push(IncorrectTokenId);
+      }
+      let balance_change = match PartialUserCommand::parse_amount_as_i64(balance_update) {
+        Ok(balance_change) => balance_change,
+        Err(e) => {
+          errors.push(e);
+          continue;
+        }
+      };
+
+      // Account creation fees are burnt, so they are left out of the balance
+      // check.
+      let account_creation_fee = balance_update.r#type == AccountCreationFeeViaPayment.to_string();
+      if account_creation_fee {
+        if token_id != DEFAULT_TOKEN_ID {
+          errors.push(IncorrectTokenId);
+        }
+        if balance_change >= 0 {
+          errors.push(AmountNotValid);
+        }
+      } else {
+        *balance_by_token.entry(token_id.clone()).or_default() += balance_change;
+      }
+
+      let token_owner = (token_id != DEFAULT_TOKEN_ID)
+        .then(|| balance_update.metadata.as_ref().and_then(|meta| meta.get("token_owner")).and_then(|t| t.as_str()))
+        .flatten()
+        .map(str::to_string);
+      account_updates.push(ZkappAccountUpdate {
+        public_key: PartialUserCommand::address_from_operation(balance_update),
+        token_id,
+        balance_change,
+        token_owner,
+        account_creation_fee,
+        signature: None,
+      });
+    }
+
+    if balance_by_token.values().any(|balance| *balance != 0) {
+      errors.push(AmountIncDecMismatch);
+    }
+
+    if !errors.is_empty() {
+      return Err(MinaMeshError::OperationsNotValid(errors));
+    }
+
+    Ok(PartialZkappCommand {
+      fee_payer: PartialUserCommand::address_from_operation(fee_payment),
+      fee: fee.unsigned_abs(),
+      account_updates,
+      valid_until,
+      memo,
+    })
+  }
+
+  /// Whether the command pays for the creation of a custom token account.
+  pub fn pays_account_creation_fee(&self) -> bool {
+    self.account_updates.iter().any(|account_update| account_update.account_creation_fee)
+  }
+
+  /// Builds the command with the given nonce. Custom token updates whose
+  /// operations do not name their token owner get `token_owner`, as looked up
+  /// by /construction/metadata.
+  pub fn to_zkapp_command_payload(
+    &self,
+    nonce: u32,
+    token_owner: Option<&str>,
+  ) -> Result<ZkappCommandPayload, MinaMeshError> {
+    address_to_compressed_pub_key("fee_payer", &self.fee_payer)?;
+    let mut account_updates = self.account_updates.clone();
+    for account_update in account_updates.iter_mut() {
+      address_to_compressed_pub_key("account_update", &account_update.public_key)?;
+      if account_update.is_custom_token() && account_update.token_owner.is_none() {
+        account_update.token_owner = Some(token_owner.map(str::to_string).ok_or_else(|| {
+          MinaMeshError::OperationsNotValid(vec![InvalidMetadata(format!(
+            "Missing token_owner for token {}",
+            account_update.token_id
+          ))])
+        })?);
+      }
+    }
+    if let Some(memo) = &self.memo {
+      Memo::from_string(memo)?;
+    }
+    let valid_until = self
+      .valid_until
+      .as_deref()
+      .map(|valid_until| {
+        valid_until
+          .parse::<u32>()
+          .map_err(|_| MinaMeshError::JsonParse(Some(format!("Invalid valid_until: {}", valid_until))))
+      })
+      .transpose()?;
+
+    Ok(ZkappCommandPayload {
+      fee_payer: ZkappFeePayer {
+        public_key: self.fee_payer.clone(),
+        fee: self.fee,
+        nonce,
+        valid_until,
+        signature: None,
+      },
+      account_updates,
+      memo: self.memo.clone(),
+    })
+  }
+
+  fn operations_of_type(operations: &[Operation], op_type: OperationType) -> Vec<&Operation> {
+    operations.iter().filter(|op| op.r#type == op_type.to_string()).collect()
+  }
+}
@@ -0,0 +1,102 @@
+use std::env;
+
+use anyhow::Result;
+use axum::{
+  body::{to_bytes, Body},
+  http::{Request, StatusCode},
+};
+use mina_mesh::{
+  create_router,
+  test::{self, network_id},
+  MinaMesh, MinaMeshConfig,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+// The GraphQL endpoint is unreachable, so that any daemon query would fail.
+async fn archive_only_mina_mesh() -> Result<MinaMesh> {
+  dotenv::dotenv().ok();
+  Ok(
+    MinaMeshConfig {
+      proxy_urls: vec!["http://wrong-graphql".to_string()],
+      archive_database_url: env::var("MINAMESH_ARCHIVE_DATABASE_URL").ok(),
+      archive_only: true,
+      ..test::config()
+    }
+    .to_mina_mesh()
+    .await?,
+  )
+}
+
+async fn post(mina_mesh: MinaMesh, uri: &str, body: Value) -> Result<(StatusCode, Value)> {
+  let request =
+    Request::post(uri).header("Content-Type", "application/json").body(Body::from(serde_json::to_vec(&body)?))?;
+  let response = create_router(mina_mesh, false).oneshot(request).await?;
+  let status = response.status();
+  let body = to_bytes(response.into_body(), usize::MAX).await?;
+  Ok((status, serde_json::from_slice(&body)?))
+}
+
+#[tokio::test]
+async fn archive_only_network_status() -> Result<()> {
+  let mina_mesh = archive_only_mina_mesh().await?;
+  let genesis_block_identifier = mina_mesh.genesis_block_identifier.clone();
+  let (status, body) = post(mina_mesh, "/network/status", json!({ "network_identifier": network_id() })).await?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body["genesis_block_identifier"], serde_json::to_value(genesis_block_identifier)?);
+  assert!(body["current_block_identifier"]["index"].as_i64().is_some());
+  assert!(body["oldest_block_identifier"]["hash"].is_string());
+  assert!(body.get("peers").is_none_or(Value::is_null));
+  Ok(())
+}
+
+#[tokio::test]
+async fn archive_only_network_list() -> Result<()> {
+  let (status, body) = post(archive_only_mina_mesh().await?, "/network/list", json!({})).await?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body["network_identifiers"][0], serde_json::to_value(network_id())?);
+  Ok(())
+}
+
+#[tokio::test]
+async fn archive_only_block() -> Result<()> {
+  let request = json!({ "network_identifier": network_id(), "block_identifier": { "index": 355393 } });
+  let (status, body) = post(archive_only_mina_mesh().await?, "/block", request).await?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body["block"]["block_identifier"]["index"], 355393);
+  Ok(())
+}
+
+#[tokio::test]
+async fn archive_only_rejects_daemon_endpoints() -> Result<()> {
+  for uri in ["/call", "/construction/metadata", "/construction/submit", "/mempool", "/mempool/transaction"] {
+    let (status, body) =
+      post(archive_only_mina_mesh().await?, uri, json!({ "network_identifier": network_id() })).await?;
+    assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+    assert_eq!(body["code"], 27);
+    assert_eq!(body["details"]["error"], format!("{} is not served in archive-only mode", uri));
+  }
+  Ok(())
+}
+
+#[tokio::test]
+async fn archive_only_rejects_current_balance() -> Result<()> {
+  let request = json!({
+    "network_identifier": network_id(),
+    // cspell:disable-next-line
+    "account_identifier": { "address": "B62qkYHGYmws5CYa3phYEKoZvrENTegEhUJYMhzHUQe5UZwCdWob8zv" },
+  });
+  let (status, body) = post(archive_only_mina_mesh().await?, "/account/balance", request).await?;
+  assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+  assert_eq!(body["code"], 27);
+  Ok(())
+}
+
+#[tokio::test]
+async fn archive_only_ready_without_daemon() -> Result<()> {
+  let report = archive_only_mina_mesh().await?.health_ready().await;
+  assert!(report.ready);
+  assert!(report.archive.is_some());
+  assert!(report.daemon.is_none());
+  Ok(())
+}
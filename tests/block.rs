@@ -4,7 +4,10 @@ use anyhow::Result;
 use futures::{stream::FuturesUnordered, StreamExt};
 use insta::assert_debug_snapshot;
 use mina_mesh::{
-  models::{BlockRequest, BlockResponse, PartialBlockIdentifier},
+  models::{
+    BlockIdentifier, BlockRequest, BlockResponse, BlockTransactionRequest, PartialBlockIdentifier,
+    TransactionIdentifier,
+  },
   test::network_id,
   MinaMeshConfig, MinaMeshError,
 };
@@ -55,3 +58,79 @@ async fn unspecified() -> Result<()> {
   assert!(response.is_ok());
   Ok(())
 }
+
+#[tokio::test]
+async fn block_transaction_matches_block() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let block_identifier =
+    BlockIdentifier::new(355393, "3NLrvv2mG7qmheEzgwCJjYbbEjLq51iCsBPJztL4JbHGFRCo9488".to_string());
+  let block = mina_mesh
+    .block(BlockRequest::new(
+      network_id(),
+      PartialBlockIdentifier { index: Some(block_identifier.index), hash: Some(block_identifier.hash.clone()) },
+    ))
+    .await?
+    .block
+    .unwrap();
+  for transaction in block.transactions {
+    let response = mina_mesh
+      .block_transaction(BlockTransactionRequest::new(
+        network_id(),
+        block_identifier.clone(),
+        (*transaction.transaction_identifier).clone(),
+      ))
+      .await?;
+    assert_eq!(*response.transaction, transaction);
+  }
+  Ok(())
+}
+
+#[tokio::test]
+async fn block_transaction_not_found() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let response = mina_mesh
+    .block_transaction(BlockTransactionRequest::new(
+      network_id(),
+      BlockIdentifier::new(355393, "3NLrvv2mG7qmheEzgwCJjYbbEjLq51iCsBPJztL4JbHGFRCo9488".to_string()),
+      TransactionIdentifier::new("unknown".to_string()),
+    ))
+    .await;
+  assert!(matches!(response, Err(MinaMeshError::TransactionNotFound(_))));
+  Ok(())
+}
+
+#[tokio::test]
+async fn max_block_transactions() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = BlockRequest::new(network_id(), specified_identifiers()[0].clone());
+  let inlined = mina_mesh.block(request.clone()).await?.block.unwrap();
+
+  // A separate instance, as the first one has the full response cached
+  let mut mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  mina_mesh.max_block_transactions = Some(1);
+  let response = mina_mesh.block(request).await?;
+  assert!(response.block.unwrap().transactions.is_empty());
+  assert_eq!(
+    response.other_transactions.unwrap(),
+    inlined.transactions.into_iter().map(|transaction| *transaction.transaction_identifier).collect::<Vec<_>>()
+  );
+  Ok(())
+}
+
+#[tokio::test]
+async fn block_cache_canonical_only() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let [by_index, by_hash, by_both] = specified_identifiers().clone();
+  let first = mina_mesh.block(BlockRequest::new(network_id(), by_index)).await?;
+  assert_eq!(mina_mesh.cache.blocks.stats().entries, 1);
+  assert_eq!(mina_mesh.cache.blocks.stats().bytes, Some(serde_json::to_vec(&first)?.len()));
+  assert_eq!(first, mina_mesh.block(BlockRequest::new(network_id(), by_hash)).await?);
+  assert_eq!(first, mina_mesh.block(BlockRequest::new(network_id(), by_both)).await?);
+  assert_eq!(mina_mesh.cache.blocks.stats().hits, 2);
+
+  // The best block is pending
+  let best = PartialBlockIdentifier { index: None, hash: None };
+  mina_mesh.block(BlockRequest::new(network_id(), best)).await?;
+  assert_eq!(mina_mesh.cache.blocks.stats().entries, 1);
+  Ok(())
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use mina_mesh::{test, Cache, CacheConfig, CacheKey, CacheStats, LruTtlCache, MinaMeshConfig, SizeBoundedLruCache};
+
+#[test]
+fn cache_evicts_least_recently_used() {
+  let cache = LruTtlCache::new("test", CacheConfig { capacity: 2, ttl: None });
+  cache.insert("a".to_string(), "1".to_string());
+  cache.insert("b".to_string(), "2".to_string());
+  assert_eq!(cache.get(&"a".to_string()), Some("1".to_string()));
+  cache.insert("c".to_string(), "3".to_string());
+  assert_eq!(cache.get(&"b".to_string()), None);
+  assert_eq!(cache.get(&"a".to_string()), Some("1".to_string()));
+  assert_eq!(cache.get(&"c".to_string()), Some("3".to_string()));
+  assert_eq!(cache.stats(), CacheStats { hits: 3, misses: 1, entries: 2, capacity: 2, bytes: None });
+}
+
+#[test]
+fn cache_expires_entries() {
+  let cache = LruTtlCache::new("test", CacheConfig { capacity: 2, ttl: Some(Duration::from_millis(10)) });
+  cache.insert(CacheKey::NetworkId, "mina:devnet".to_string());
+  assert_eq!(cache.get(&CacheKey::NetworkId), Some("mina:devnet".to_string()));
+  std::thread::sleep(Duration::from_millis(20));
+  assert_eq!(cache.get(&CacheKey::NetworkId), None);
+  assert_eq!(cache.stats().entries, 0);
+}
+
+#[test]
+fn cache_disabled_with_zero_capacity() {
+  let cache = LruTtlCache::new("test", CacheConfig { capacity: 0, ttl: None });
+  cache.insert("a".to_string(), "1".to_string());
+  assert_eq!(cache.get(&"a".to_string()), None);
+  assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 1, entries: 0, capacity: 0, bytes: None });
+}
+
+#[test]
+fn cache_bounded_by_size() {
+  let cache = SizeBoundedLruCache::new("test", 10, String::len);
+  cache.insert("a".to_string(), "1234".to_string());
+  cache.insert("b".to_string(), "1234".to_string());
+  assert_eq!(cache.get(&"a".to_string()), Some("1234".to_string()));
+  cache.insert("c".to_string(), "1234".to_string());
+  assert_eq!(cache.get(&"b".to_string()), None);
+  // Larger than the whole cache
+  cache.insert("d".to_string(), "12345678901".to_string());
+  assert_eq!(cache.get(&"d".to_string()), None);
+  assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2, entries: 2, capacity: 10, bytes: Some(8) });
+}
+
+#[tokio::test]
+async fn cache_capacities_from_config() -> Result<()> {
+  let mina_mesh = MinaMeshConfig { offline: true, network_id_cache_size: 4, ..test::config() }.to_mina_mesh().await?;
+  let network_id = mina_mesh.cache_stats().into_iter().find(|(name, _)| *name == "network_id").map(|(_, stats)| stats);
+  assert_eq!(network_id.map(|stats| stats.capacity), Some(4));
+  Ok(())
+}
@@ -0,0 +1,90 @@
+use std::env;
+
+use anyhow::Result;
+use axum::{
+  body::{to_bytes, Body},
+  http::Request,
+};
+use mina_mesh::{
+  create_router,
+  models::{CallRequest, TransactionIdentifier},
+  test::{self, mock_daemon, network_id},
+  MinaMeshConfig, MinaMeshError,
+};
+use serde_json::json;
+use tower::ServiceExt;
+
+fn transaction_status_request(hash: &str) -> CallRequest {
+  CallRequest::new(
+    network_id(),
+    "transaction_status".to_string(),
+    json!({ "transaction_identifier": TransactionIdentifier::new(hash.to_string()) }),
+  )
+}
+
+#[tokio::test]
+async fn call_transaction_status_canonical() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  // cspell:disable-next-line
+  let response =
+    mina_mesh.call(transaction_status_request("5JvFoEyvuPu9zmi4bDGbhqsakre2SPQU1KKbeh2Lk5uC9eYrc2h2")).await?;
+  assert!(!response.idempotent);
+  assert_eq!(response.result["status"], "canonical");
+  assert!(response.result["block_identifier"]["index"].is_i64());
+  assert!(response.result["depth"].as_i64().is_some_and(|depth| depth >= 0));
+  Ok(())
+}
+
+#[tokio::test]
+async fn call_transaction_status_unknown() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  // cspell:disable-next-line
+  let response =
+    mina_mesh.call(transaction_status_request("5JtkDpZT7Ad1oM7oxKEvpvq1ZDXZBAmpVQCEX1sRtYe4zPMTdVSy")).await?;
+  assert_eq!(response.result["status"], "unknown");
+  assert!(response.result.get("block_identifier").is_none());
+  Ok(())
+}
+
+#[tokio::test]
+async fn call_transaction_status_pending_zkapp_command() -> Result<()> {
+  dotenv::dotenv().ok();
+  // cspell:disable-next-line
+  let hash = "5JtkDpZT7Ad1oM7oxKEvpvq1ZDXZBAmpVQCEX1sRtYe4zPMTdVSy";
+  let mina_mesh = MinaMeshConfig {
+    proxy_urls: vec![
+      mock_daemon(json!({
+        "pooledUserCommands": [],
+        "pooledZkappCommands": [{ "hash": hash }]
+      }))
+      .await?,
+    ],
+    archive_database_url: env::var("MINAMESH_ARCHIVE_DATABASE_URL").ok(),
+    ..test::config()
+  }
+  .to_mina_mesh()
+  .await?;
+  let response = mina_mesh.call(transaction_status_request(hash)).await?;
+  assert_eq!(response.result["status"], "pending");
+  assert!(response.result.get("block_identifier").is_none());
+  Ok(())
+}
+
+#[tokio::test]
+async fn call_unsupported_method() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = CallRequest::new(network_id(), "unsupported".to_string(), json!({}));
+  let response = mina_mesh.call(request).await;
+  assert!(matches!(response, Err(MinaMeshError::JsonParse(Some(message))) if message.contains("unsupported")));
+  Ok(())
+}
+
+#[tokio::test]
+async fn call_available_endpoint() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = Request::get("/available_endpoints").body(Body::empty())?;
+  let response = create_router(mina_mesh, false).oneshot(request).await?;
+  let endpoints: Vec<String> = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await?)?;
+  assert!(endpoints.iter().any(|endpoint| endpoint == "/call"));
+  Ok(())
+}
@@ -1,8 +1,9 @@
-mod fixtures;
-
 use anyhow::Result;
 use futures::future::join_all;
-use mina_mesh::{test::ResponseComparisonContext, MinaMeshConfig};
+use mina_mesh::{
+  test::{fixtures, ResponseComparisonContext},
+  MinaMeshConfig,
+};
 use serde::Serialize;
 
 const LEGACY_ENDPOINT: &str = "https://rosetta-devnet.minaprotocol.network";
@@ -2,8 +2,8 @@ use anyhow::Result;
 use coinbase_mesh::models::ConstructionCombineRequest;
 use insta::assert_debug_snapshot;
 use mina_mesh::{
-  models::SignatureType,
-  test::{network_id, signature, unsigned_transaction_delegation, unsigned_transaction_payment},
+  models::{NetworkIdentifier, SignatureType},
+  test::{self, network_id, signature, unsigned_transaction_delegation, unsigned_transaction_payment},
   MinaMeshConfig, MinaMeshError,
 };
 
@@ -57,7 +57,7 @@ async fn construction_combine_invalid_signature_format() -> Result<()> {
 async fn construction_combine_valid_payment() -> Result<()> {
   let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
   // cspell:disable-next-line
-  let sig_hex = "CA5B636101409503297B02AB94DE28FACBD53C91919A77E57CCBEDBF9D6C2D1893FDE9B63BF44618270F6D404B7C4B783BB322B05C9347BEF238FDB841BF3320";
+  let sig_hex = "52DA947A59B79B62FB0E42BDB49390FFF43AA2997DFC415B78CD3097E15D0221D807069A35BE13D62A5B45F8590A7CC8684E45B076F8BC5F1E711442FA1A6506";
   let request = ConstructionCombineRequest {
     network_identifier: network_id().into(),
     unsigned_transaction: unsigned_transaction_payment(),
@@ -73,7 +73,7 @@ async fn construction_combine_valid_payment() -> Result<()> {
 async fn construction_combine_valid_delegation() -> Result<()> {
   let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
   // cspell:disable-next-line
-  let sig_hex = "CA5B636101409503297B02AB94DE28FACBD53C91919A77E57CCBEDBF9D6C2D1893FDE9B63BF44618270F6D404B7C4B783BB322B05C9347BEF238FDB841BF3320";
+  let sig_hex = "549E0B6AD43D1E894EBEE9255FEDC1C248CC947F0B548FE309ADDF5C35A95E2783390A8A5EA76561FB0EFEA9F12999FD3D6F9C41FEFEDB7D1D953C1F1861F412";
   let request = ConstructionCombineRequest {
     network_identifier: network_id().into(),
     unsigned_transaction: unsigned_transaction_delegation(),
@@ -84,3 +84,39 @@ async fn construction_combine_valid_delegation() -> Result<()> {
   assert_debug_snapshot!(response);
   Ok(())
 }
+
+#[tokio::test]
+async fn construction_combine_signature_mismatch() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  // Signature over the delegation, attached to the payment
+  // cspell:disable-next-line
+  let sig_hex = "549E0B6AD43D1E894EBEE9255FEDC1C248CC947F0B548FE309ADDF5C35A95E2783390A8A5EA76561FB0EFEA9F12999FD3D6F9C41FEFEDB7D1D953C1F1861F412";
+  let request = ConstructionCombineRequest {
+    network_identifier: network_id().into(),
+    unsigned_transaction: unsigned_transaction_payment(),
+    signatures: vec![signature(sig_hex, SignatureType::SchnorrPoseidon)],
+  };
+  let response = mina_mesh.construction_combine(request).await;
+  assert!(matches!(response, Err(MinaMeshError::SignatureInvalid(_))));
+  Ok(())
+}
+
+#[tokio::test]
+async fn construction_combine_wrong_network_domain() -> Result<()> {
+  // A mainnet server, so that the network identifier is valid but the signature
+  // is verified in the mainnet domain
+  let mina_mesh = MinaMeshConfig { offline: true, network_id: Some("mina:mainnet".to_string()), ..test::config() }
+    .to_mina_mesh()
+    .await?;
+  // Valid payment signature in the testnet domain
+  // cspell:disable-next-line
+  let sig_hex = "52DA947A59B79B62FB0E42BDB49390FFF43AA2997DFC415B78CD3097E15D0221D807069A35BE13D62A5B45F8590A7CC8684E45B076F8BC5F1E711442FA1A6506";
+  let request = ConstructionCombineRequest {
+    network_identifier: NetworkIdentifier::new("mina".to_string(), "mainnet".to_string()).into(),
+    unsigned_transaction: unsigned_transaction_payment(),
+    signatures: vec![signature(sig_hex, SignatureType::SchnorrPoseidon)],
+  };
+  let response = mina_mesh.construction_combine(request).await;
+  assert!(matches!(response, Err(MinaMeshError::SignatureInvalid(message)) if message.contains("mina:mainnet")));
+  Ok(())
+}
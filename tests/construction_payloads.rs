@@ -1,9 +1,10 @@
 use anyhow::Result;
-use coinbase_mesh::models::ConstructionPayloadsRequest;
+use coinbase_mesh::models::{ConstructionParseRequest, ConstructionPayloadsRequest};
 use insta::assert_debug_snapshot;
 use mina_mesh::{
-  test::{delegation_operations, network_id, payment_operations},
-  MinaMeshConfig, TransactionMetadata,
+  test::{delegation_operations, network_id, payment_operations, zkapp_operations},
+  util::DEFAULT_TOKEN_ID,
+  MinaMeshConfig, MinaMeshError, PartialReason, TransactionMetadata, TransactionUnsigned,
 };
 
 #[tokio::test]
@@ -87,3 +88,154 @@ async fn construction_payloads_delegation() -> Result<()> {
   assert_debug_snapshot!(response);
   Ok(())
 }
+
+#[tokio::test]
+async fn construction_payloads_zkapp() -> Result<()> {
+  // cspell:disable
+  let fee_payer = "B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk";
+  let sender = "B62qkXajxfnicuCNtaurdAhQpkFsqjoyPJuw53aeJP848bsa3Ne3RvB";
+  let receiver = "B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv";
+  // cspell:enable
+  let metadata = TransactionMetadata::new(
+    fee_payer,
+    receiver,
+    "3",
+    DEFAULT_TOKEN_ID,
+    None::<&str>,
+    Some("20000"),
+    Some("zkapp memo"),
+  );
+  let operations =
+    zkapp_operations((fee_payer, "-10000000"), &[(sender, "-50000"), (receiver, "50000")], DEFAULT_TOKEN_ID);
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = ConstructionPayloadsRequest {
+    network_identifier: network_id().into(),
+    operations: operations.clone(),
+    metadata: Some(metadata.to_json()),
+    public_keys: None,
+  };
+  let response = mina_mesh.construction_payloads(request).await?;
+
+  // The fee payer and the debited account both sign the full commitment.
+  let signers: Vec<_> =
+    response.payloads.iter().map(|payload| payload.account_identifier.as_ref().unwrap().address.as_str()).collect();
+  assert_eq!(signers, vec![fee_payer, sender]);
+  assert_eq!(response.payloads[0].hex_bytes, response.payloads[1].hex_bytes);
+  assert_eq!(response.payloads[0].hex_bytes.len(), 64);
+
+  let unsigned_transaction = TransactionUnsigned::from_json_string(&response.unsigned_transaction)?;
+  let zkapp_command = unsigned_transaction.zkapp_command.unwrap();
+  assert_eq!(zkapp_command.fee_payer.nonce, 3);
+  assert_eq!(zkapp_command.fee_payer.valid_until, Some(20000));
+  assert_eq!(zkapp_command.memo.as_deref(), Some("zkapp memo"));
+
+  let parse_request = ConstructionParseRequest {
+    network_identifier: network_id().into(),
+    transaction: response.unsigned_transaction,
+    signed: false,
+  };
+  let parsed = mina_mesh.construction_parse(parse_request).await?;
+  let parsed_operations: Vec<_> =
+    parsed.operations.iter().map(|op| (op.r#type.clone(), op.amount.as_ref().unwrap().value.clone())).collect();
+  let expected_operations: Vec<_> =
+    operations.iter().map(|op| (op.r#type.clone(), op.amount.as_ref().unwrap().value.clone())).collect();
+  assert_eq!(parsed_operations, expected_operations);
+  Ok(())
+}
+
+#[tokio::test]
+async fn construction_payloads_zkapp_legacy_token_id() -> Result<()> {
+  // cspell:disable
+  let fee_payer = "B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk";
+  let receiver = "B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv";
+  // cspell:enable
+  let metadata = TransactionMetadata::new(fee_payer, receiver, "3", "1", None::<&str>, None::<&str>, None::<&str>);
+  let operations = zkapp_operations((fee_payer, "-10000000"), &[(fee_payer, "-50000"), (receiver, "50000")], "1");
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = ConstructionPayloadsRequest {
+    network_identifier: network_id().into(),
+    operations,
+    metadata: Some(metadata.to_json()),
+    public_keys: None,
+  };
+  let response = mina_mesh.construction_payloads(request).await?;
+  let unsigned_transaction = TransactionUnsigned::from_json_string(&response.unsigned_transaction)?;
+  let zkapp_command = unsigned_transaction.zkapp_command.unwrap();
+  assert!(zkapp_command.account_updates.iter().all(|update| update.token_id == DEFAULT_TOKEN_ID));
+  Ok(())
+}
+
+#[tokio::test]
+async fn construction_payloads_zkapp_invalid_valid_until() -> Result<()> {
+  // cspell:disable
+  let fee_payer = "B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk";
+  let receiver = "B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv";
+  // cspell:enable
+  let metadata =
+    TransactionMetadata::new(fee_payer, receiver, "3", DEFAULT_TOKEN_ID, None::<&str>, Some("-1"), None::<&str>);
+  let operations =
+    zkapp_operations((fee_payer, "-10000000"), &[(fee_payer, "-50000"), (receiver, "50000")], DEFAULT_TOKEN_ID);
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = ConstructionPayloadsRequest {
+    network_identifier: network_id().into(),
+    operations,
+    metadata: Some(metadata.to_json()),
+    public_keys: None,
+  };
+  let response = mina_mesh.construction_payloads(request).await;
+  assert_eq!(response.unwrap_err(), MinaMeshError::JsonParse(Some("Invalid valid_until: -1".to_string())));
+  Ok(())
+}
+
+#[tokio::test]
+async fn construction_payloads_zkapp_token_missing_owner() -> Result<()> {
+  // cspell:disable
+  let fee_payer = "B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk";
+  let receiver = "B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv";
+  let token_id = "xBxjFpJkbWpbGua7Lf36S1NLhffFoEChyP3pz6SYKnx7dFCTwg";
+  // cspell:enable
+  let metadata = TransactionMetadata::new(fee_payer, receiver, "3", token_id, None::<&str>, None::<&str>, None::<&str>);
+  let operations = zkapp_operations((fee_payer, "-10000000"), &[(fee_payer, "-50000"), (receiver, "50000")], token_id);
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = ConstructionPayloadsRequest {
+    network_identifier: network_id().into(),
+    operations,
+    metadata: Some(metadata.to_json()),
+    public_keys: None,
+  };
+  let response = mina_mesh.construction_payloads(request).await;
+  assert_eq!(
+    response.unwrap_err(),
+    MinaMeshError::OperationsNotValid(vec![PartialReason::InvalidMetadata(format!(
+      "Missing token_owner for token {}",
+      token_id
+    ))])
+  );
+  Ok(())
+}
+
+#[tokio::test]
+async fn construction_payloads_zkapp_token_account_creation_fee_required() -> Result<()> {
+  // cspell:disable
+  let fee_payer = "B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk";
+  let receiver = "B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv";
+  let token_id = "xBxjFpJkbWpbGua7Lf36S1NLhffFoEChyP3pz6SYKnx7dFCTwg";
+  // cspell:enable
+  let metadata =
+    TransactionMetadata::new(fee_payer, receiver, "3", token_id, Some("1000000000"), None::<&str>, None::<&str>)
+      .with_token_owner(Some(fee_payer));
+  let operations = zkapp_operations((fee_payer, "-10000000"), &[(fee_payer, "-50000"), (receiver, "50000")], token_id);
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = ConstructionPayloadsRequest {
+    network_identifier: network_id().into(),
+    operations,
+    metadata: Some(metadata.to_json()),
+    public_keys: None,
+  };
+  let response = mina_mesh.construction_payloads(request).await;
+  assert!(matches!(
+    response.unwrap_err(),
+    MinaMeshError::OperationsNotValid(reasons) if matches!(reasons[..], [PartialReason::InvalidMetadata(_)])
+  ));
+  Ok(())
+}
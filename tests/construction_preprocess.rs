@@ -2,8 +2,9 @@ use anyhow::Result;
 use insta::assert_debug_snapshot;
 use mina_mesh::{
   models::ConstructionPreprocessRequest,
-  test::{delegation_operations, network_id, payment_operations},
-  MinaMeshConfig, PreprocessMetadata,
+  test::{delegation_operations, network_id, payment_operations, zkapp_operations},
+  util::DEFAULT_TOKEN_ID,
+  MinaMeshConfig, MinaMeshError, PartialReason, PreprocessMetadata,
 };
 
 #[tokio::test]
@@ -235,3 +236,67 @@ async fn construction_preprocess_delegation_invalid_pk() -> Result<()> {
   assert_debug_snapshot!(response);
   Ok(())
 }
+
+#[tokio::test]
+async fn construction_preprocess_zkapp() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let operations = zkapp_operations(
+    // cspell:disable
+    ("B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk", "-1010"),
+    &[
+      ("B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk", "-50000"),
+      ("B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv", "50000"),
+    ],
+    // cspell:enable
+    DEFAULT_TOKEN_ID,
+  );
+  let request = ConstructionPreprocessRequest::new(network_id(), operations);
+  let options = mina_mesh.construction_preprocess(request).await?.options.unwrap();
+  // cspell:disable
+  assert_eq!(options["sender"], "B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk");
+  assert_eq!(options["receiver"], "B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv");
+  // cspell:enable
+  assert_eq!(options["token_id"], DEFAULT_TOKEN_ID);
+  Ok(())
+}
+
+#[tokio::test]
+async fn construction_preprocess_zkapp_balance_mismatch() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let operations = zkapp_operations(
+    // cspell:disable
+    ("B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk", "-1010"),
+    &[
+      ("B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk", "-50000"),
+      ("B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv", "40000"),
+    ],
+    // cspell:enable
+    DEFAULT_TOKEN_ID,
+  );
+  let request = ConstructionPreprocessRequest::new(network_id(), operations);
+  let response = mina_mesh.construction_preprocess(request).await;
+  assert_eq!(response.unwrap_err(), MinaMeshError::OperationsNotValid(vec![PartialReason::AmountIncDecMismatch]));
+  Ok(())
+}
+
+#[tokio::test]
+async fn construction_preprocess_payment_custom_token() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  // cspell:disable
+  let sender = "B62qkUHaJUHERZuCHQhXCQ8xsGBqyYSgjQsKnKN5HhSJecakuJ4pYyk";
+  let receiver = "B62qoDWfBZUxKpaoQCoFqr12wkaY84FrhxXNXzgBkMUi2Tz4K8kBDiv";
+  let token_id = "xBxjFpJkbWpbGua7Lf36S1NLhffFoEChyP3pz6SYKnx7dFCTwg";
+  // cspell:enable
+  let mut operations = payment_operations((sender, "-1010"), (sender, "-50000"), (receiver, "50000"));
+  for operation in operations.iter_mut().skip(1) {
+    operation.account.as_mut().unwrap().metadata = Some(serde_json::json!({ "token_id": token_id }));
+  }
+  let request = ConstructionPreprocessRequest::new(network_id(), operations);
+  let response = mina_mesh.construction_preprocess(request).await;
+  let MinaMeshError::OperationsNotValid(reasons) = response.unwrap_err() else { panic!("Expected OperationsNotValid") };
+  assert!(reasons.contains(&PartialReason::InvalidMetadata(format!(
+    "Token {} can only be transferred with zkapp_balance_update operations",
+    token_id
+  ))));
+  Ok(())
+}
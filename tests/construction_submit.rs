@@ -1,7 +1,10 @@
 use anyhow::Result;
-use coinbase_mesh::models::ConstructionSubmitRequest;
+use coinbase_mesh::models::{ConstructionHashRequest, ConstructionSubmitRequest};
 use insta::assert_debug_snapshot;
-use mina_mesh::{test::network_id, MinaMeshConfig};
+use mina_mesh::{
+  test::{network_id, signed_transaction_payment},
+  MinaMeshConfig,
+};
 
 #[tokio::test]
 async fn construction_submit_empty() -> Result<()> {
@@ -232,3 +235,24 @@ async fn construction_submit_insufficient_fee() -> Result<()> {
   assert_debug_snapshot!(response);
   Ok(())
 }
+
+#[tokio::test]
+async fn construction_submit_resubmission_is_idempotent() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+
+  let hash_request = ConstructionHashRequest {
+    network_identifier: network_id().into(),
+    signed_transaction: signed_transaction_payment(),
+  };
+  let hash = mina_mesh.construction_hash(hash_request).await?.transaction_identifier.hash;
+  // Recorded as if submitted before, so the resubmission never reaches the daemon
+  mina_mesh.submissions.insert(&hash, &hash)?;
+
+  let request = ConstructionSubmitRequest {
+    network_identifier: network_id().into(),
+    signed_transaction: signed_transaction_payment(),
+  };
+  let response = mina_mesh.construction_submit(request).await?;
+  assert_eq!(response.hash, hash);
+  Ok(())
+}
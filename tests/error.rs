@@ -1,7 +1,7 @@
 use std::env;
 
 use axum::{body::to_bytes, http::StatusCode, response::IntoResponse};
-use mina_mesh::{MinaMeshConfig, MinaMeshError};
+use mina_mesh::{test, MinaMeshConfig, MinaMeshError};
 
 async fn assert_error_properties(
   error: MinaMeshError,
@@ -160,6 +160,27 @@ async fn test_error_properties() {
       false,
       StatusCode::BAD_REQUEST,
     ),
+    (
+      EndpointUnavailable("/block is not served in offline mode".to_string()),
+      27,
+      "This endpoint is not available with the current server configuration.",
+      false,
+      StatusCode::NOT_IMPLEMENTED,
+    ),
+    (
+      SqlTimeout("canceling statement due to statement timeout".to_string()),
+      28,
+      "A SQL query took too long and was cancelled.",
+      true,
+      StatusCode::GATEWAY_TIMEOUT,
+    ),
+    (
+      InvalidCursor("A cursor cannot be combined with an offset".to_string()),
+      29,
+      "The search cursor is malformed or cannot be used with the other parameters of the request.",
+      false,
+      StatusCode::BAD_REQUEST,
+    ),
   ];
 
   for (error, code, description, retriable, status) in cases {
@@ -187,11 +208,10 @@ fn test_conversion_from_parse_int_error() {
 async fn test_conversion_from_cynic_reqwest_error() -> Result<(), MinaMeshError> {
   dotenv::dotenv().ok();
   let res = MinaMeshConfig {
-    proxy_url: "http://wrong-graphql".to_string(),
-    archive_database_url: env::var("MINAMESH_ARCHIVE_DATABASE_URL").unwrap(),
-    max_db_pool_size: 10,
-    db_pool_idle_timeout: 1,
-    use_search_tx_optimizations: false,
+    proxy_urls: vec!["http://wrong-graphql".to_string()],
+    archive_database_url: env::var("MINAMESH_ARCHIVE_DATABASE_URL").ok(),
+    network_id: None,
+    ..test::config()
   }
   .to_mina_mesh()
   .await;
@@ -212,11 +232,10 @@ fn test_conversion_from_anyhow_error() {
 async fn test_graphql_uri_not_set_error() -> Result<(), MinaMeshError> {
   dotenv::dotenv().ok();
   let res = MinaMeshConfig {
-    proxy_url: "".to_string(),
-    archive_database_url: env::var("MINAMESH_ARCHIVE_DATABASE_URL").unwrap(),
-    max_db_pool_size: 10,
-    db_pool_idle_timeout: 1,
-    use_search_tx_optimizations: false,
+    proxy_urls: vec![],
+    archive_database_url: env::var("MINAMESH_ARCHIVE_DATABASE_URL").ok(),
+    network_id: None,
+    ..test::config()
   }
   .to_mina_mesh()
   .await;
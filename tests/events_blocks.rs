@@ -0,0 +1,52 @@
+use anyhow::Result;
+use mina_mesh::{
+  models::{BlockEventType, EventsBlocksRequest},
+  test::network_id,
+  util::MAX_BLOCK_EVENTS_LIMIT,
+  MinaMeshConfig,
+};
+
+#[tokio::test]
+async fn events_blocks_offset_limit() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = EventsBlocksRequest { network_identifier: Box::new(network_id()), offset: Some(0), limit: Some(10) };
+  let response = mina_mesh.events_blocks(request).await?;
+
+  assert_eq!(response.events.len(), 10);
+  assert!(response.events.windows(2).all(|pair| pair[0].sequence < pair[1].sequence));
+  assert!(response.events.iter().all(|event| event.sequence <= response.max_sequence));
+
+  let next_offset = response.events.last().unwrap().sequence + 1;
+  let request =
+    EventsBlocksRequest { network_identifier: Box::new(network_id()), offset: Some(next_offset), limit: Some(10) };
+  let next_page = mina_mesh.events_blocks(request).await?;
+  assert!(next_page.events.iter().all(|event| event.sequence >= next_offset));
+  Ok(())
+}
+
+#[tokio::test]
+async fn events_blocks_latest_without_offset() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = EventsBlocksRequest { network_identifier: Box::new(network_id()), offset: None, limit: Some(10) };
+  let response = mina_mesh.events_blocks(request).await?;
+
+  // The latest events end at the highest canonical block
+  assert_eq!(response.events.len(), 10);
+  assert_eq!(response.events.last().unwrap().sequence, response.max_sequence);
+  for event in &response.events {
+    assert_eq!(event.r#type, BlockEventType::BlockAdded);
+    assert_eq!(event.sequence, event.block_identifier.index);
+  }
+  Ok(())
+}
+
+#[tokio::test]
+async fn events_blocks_limit_capped() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request =
+    EventsBlocksRequest { network_identifier: Box::new(network_id()), offset: Some(0), limit: Some(1_000_000) };
+  let response = mina_mesh.events_blocks(request).await?;
+
+  assert!(response.events.len() as i64 <= MAX_BLOCK_EVENTS_LIMIT);
+  Ok(())
+}
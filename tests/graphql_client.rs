@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use mina_mesh::{
+  test::{self, network_request},
+  MinaMesh, MinaMeshConfig, MinaMeshError,
+};
+
+// Nothing listens on the discard port, so every request fails to connect. The
+// network status is queried, as the configured network id is never queried.
+async fn unreachable_daemon_mina_mesh(graphql_max_retries: u32) -> Result<MinaMesh> {
+  Ok(
+    MinaMeshConfig {
+      proxy_urls: vec!["http://127.0.0.1:9/graphql".to_string()],
+      offline: true,
+      graphql_timeout: 1,
+      graphql_max_retries,
+      graphql_retry_backoff_ms: 100,
+      graphql_circuit_breaker_threshold: 3,
+      graphql_circuit_breaker_cooldown: 60,
+      ..test::config()
+    }
+    .to_mina_mesh()
+    .await?,
+  )
+}
+
+fn is_unavailable<T>(response: Result<T, MinaMeshError>) -> bool {
+  matches!(
+    response,
+    Err(MinaMeshError::GraphqlMinaQuery(message)) if message.contains("unavailable after 3 consecutive failures")
+  )
+}
+
+#[tokio::test]
+async fn graphql_client_retries_queries() -> Result<()> {
+  let mina_mesh = unreachable_daemon_mina_mesh(2).await?;
+  let start = Instant::now();
+  let response = mina_mesh.network_status(network_request()).await;
+  assert!(matches!(response, Err(MinaMeshError::GraphqlMinaQuery(_))));
+  // Two retries, after 100ms and 200ms
+  assert!(start.elapsed().as_millis() >= 300);
+  Ok(())
+}
+
+#[tokio::test]
+async fn graphql_client_circuit_breaker_fails_fast() -> Result<()> {
+  let mina_mesh = unreachable_daemon_mina_mesh(5).await?;
+
+  // The third failed attempt opens the circuit, which ends the retries after
+  // 100ms and 200ms instead of all five
+  let start = Instant::now();
+  assert!(is_unavailable(mina_mesh.network_status(network_request()).await));
+  assert!(start.elapsed() < Duration::from_millis(1000));
+
+  // Requests fail fast while the circuit is open, with neither attempts nor
+  // backoff
+  let start = Instant::now();
+  assert!(is_unavailable(mina_mesh.network_status(network_request()).await));
+  assert!(start.elapsed() < Duration::from_millis(50));
+  Ok(())
+}
+
+#[tokio::test]
+async fn graphql_client_fails_over_to_synced_endpoint() -> Result<()> {
+  let mut config = MinaMeshConfig::from_env();
+  config.proxy_urls.insert(0, "http://127.0.0.1:9/graphql".to_string());
+  let mina_mesh = config.to_mina_mesh().await?;
+  let response = mina_mesh.network_list().await?;
+  assert_eq!(response.network_identifiers.len(), 1);
+  Ok(())
+}
@@ -0,0 +1,96 @@
+use std::env;
+
+use anyhow::Result;
+use axum::{
+  body::{to_bytes, Body},
+  http::{Request, StatusCode},
+};
+use mina_mesh::{
+  create_router,
+  test::{self, mock_daemon},
+  MinaMesh, MinaMeshConfig,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn offline_mina_mesh() -> Result<MinaMesh> {
+  Ok(MinaMeshConfig { offline: true, ..test::config() }.to_mina_mesh().await?)
+}
+
+async fn get(mina_mesh: MinaMesh, uri: &str) -> Result<(StatusCode, Value)> {
+  let response = create_router(mina_mesh, false).oneshot(Request::get(uri).body(Body::empty())?).await?;
+  let status = response.status();
+  let body = to_bytes(response.into_body(), usize::MAX).await?;
+  Ok((status, serde_json::from_slice(&body)?))
+}
+
+#[tokio::test]
+async fn health_live() -> Result<()> {
+  let (status, body) = get(offline_mina_mesh().await?, "/health/live").await?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body, json!({ "status": "ok" }));
+  Ok(())
+}
+
+#[tokio::test]
+async fn health_ready_offline() -> Result<()> {
+  let (status, body) = get(offline_mina_mesh().await?, "/health/ready").await?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body, json!({ "ready": true }));
+  Ok(())
+}
+
+#[tokio::test]
+async fn health_ready_reports_dependencies() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let report = mina_mesh.health_ready().await;
+  let archive = report.archive.expect("archive is checked online");
+  let daemon = report.daemon.expect("daemon is checked online");
+  let tip_drift = report.tip_drift.expect("tip drift is checked online");
+  assert!(archive.healthy, "archive should be reachable: {:?}", archive.error);
+  assert!(daemon.height.is_some(), "daemon should report its best tip: {:?}", daemon.error);
+  assert!(daemon.sync_status.is_some());
+  assert_eq!(tip_drift.drift, Some(daemon.height.unwrap() - archive.height.unwrap()));
+  assert_eq!(report.ready, daemon.healthy && tip_drift.healthy);
+  Ok(())
+}
+
+#[tokio::test]
+async fn health_ready_not_ready_on_tip_drift() -> Result<()> {
+  let mut mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  mina_mesh.max_tip_drift = 0;
+  let report = mina_mesh.health_ready().await;
+  let tip_drift = report.tip_drift.expect("tip drift is checked online");
+  if tip_drift.drift.is_some_and(|drift| drift > 0) {
+    assert!(!tip_drift.healthy);
+    assert!(!report.ready);
+  }
+  Ok(())
+}
+
+#[tokio::test]
+async fn health_ready_synced_daemon_at_archive_tip() -> Result<()> {
+  let archive_height =
+    MinaMeshConfig::from_env().to_mina_mesh().await?.health_ready().await.archive.and_then(|archive| archive.height);
+  let archive_height = archive_height.expect("archive should report its highest block");
+  let best_chain = json!([{
+    // cspell:disable-next-line
+    "stateHash": "3NKeMoncuHab5ScarV5ViyF16cJPT4taWNSaTLS64Dp67wuXigPZ",
+    "protocolState": {
+      "blockchainState": { "utcDate": "0" },
+      "consensusState": { "blockHeight": archive_height.to_string() }
+    }
+  }]);
+  let daemon = mock_daemon(json!({ "bestChain": best_chain, "daemonStatus": { "peers": [] }, "syncStatus": "SYNCED" }));
+  let mina_mesh = MinaMeshConfig {
+    proxy_urls: vec![daemon.await?],
+    archive_database_url: env::var("MINAMESH_ARCHIVE_DATABASE_URL").ok(),
+    ..test::config()
+  }
+  .to_mina_mesh()
+  .await?;
+  let report = mina_mesh.health_ready().await;
+  assert_eq!(report.tip_drift.and_then(|tip_drift| tip_drift.drift), Some(0));
+  assert!(report.ready);
+  Ok(())
+}
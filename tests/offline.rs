@@ -0,0 +1,66 @@
+use anyhow::Result;
+use axum::{
+  body::{to_bytes, Body},
+  http::{Request, StatusCode},
+};
+use mina_mesh::{
+  create_router,
+  models::{ConstructionDeriveRequest, CurveType::Tweedle, PublicKey},
+  test::{self, network_id},
+  MinaMesh, MinaMeshConfig,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+async fn offline_mina_mesh() -> Result<MinaMesh> {
+  Ok(MinaMeshConfig { offline: true, ..test::config() }.to_mina_mesh().await?)
+}
+
+async fn post(mina_mesh: MinaMesh, uri: &str, body: Value) -> Result<(StatusCode, Value)> {
+  let request =
+    Request::post(uri).header("Content-Type", "application/json").body(Body::from(serde_json::to_vec(&body)?))?;
+  let response = create_router(mina_mesh, false).oneshot(request).await?;
+  let status = response.status();
+  let body = to_bytes(response.into_body(), usize::MAX).await?;
+  Ok((status, serde_json::from_slice(&body)?))
+}
+
+#[tokio::test]
+async fn offline_construction_derive() -> Result<()> {
+  let mina_mesh = offline_mina_mesh().await?;
+  let request = ConstructionDeriveRequest::new(
+    network_id(),
+    PublicKey::new(
+      // cspell:disable-next-line
+      "3C2B5B48C22DC8B8C9D2C9D76A2CEAAF02BEABB364301726C3F8E989653AF513".to_string(),
+      Tweedle,
+    ),
+  );
+  let (status, _) = post(mina_mesh, "/construction/derive", serde_json::to_value(request)?).await?;
+  assert_eq!(status, StatusCode::OK);
+  Ok(())
+}
+
+#[tokio::test]
+async fn offline_network_endpoints() -> Result<()> {
+  let (status, body) = post(offline_mina_mesh().await?, "/network/list", json!({})).await?;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body["network_identifiers"][0], serde_json::to_value(network_id())?);
+  let (status, body) =
+    post(offline_mina_mesh().await?, "/network/options", json!({ "network_identifier": network_id() })).await?;
+  assert_eq!(status, StatusCode::OK);
+  assert!(body["allow"]["operation_types"].as_array().is_some_and(|types| !types.is_empty()));
+  Ok(())
+}
+
+#[tokio::test]
+async fn offline_rejects_online_endpoints() -> Result<()> {
+  for uri in ["/block", "/construction/metadata", "/construction/submit", "/network/status"] {
+    let mina_mesh = offline_mina_mesh().await?;
+    let (status, body) = post(mina_mesh, uri, json!({ "network_identifier": network_id() })).await?;
+    assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+    assert_eq!(body["code"], 27);
+    assert_eq!(body["details"]["error"], format!("{} is not served in offline mode", uri));
+  }
+  Ok(())
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use axum::{
+  body::{to_bytes, Body},
+  http::{Request, StatusCode},
+};
+use mina_mesh::{create_router, operation_types, test::fixtures, MinaMesh, MinaMeshConfig, MinaMeshError};
+use serde_json::Value;
+use tower::ServiceExt;
+
+async fn openapi_document(mina_mesh: MinaMesh) -> Result<Value> {
+  let request = Request::get("/openapi.json").body(Body::empty())?;
+  let response = create_router(mina_mesh, false).oneshot(request).await?;
+  assert_eq!(response.status(), StatusCode::OK);
+  let body = to_bytes(response.into_body(), usize::MAX).await?;
+  Ok(serde_json::from_slice(&body)?)
+}
+
+#[tokio::test]
+async fn openapi_documents_mina() -> Result<()> {
+  let document = openapi_document(MinaMeshConfig::from_env().to_mina_mesh().await?).await?;
+  assert_eq!(document["paths"].as_object().map(|paths| paths.len()), Some(19));
+  assert!(document["paths"]["/construction/submit"]["post"]["responses"]["default"].is_object());
+  let schemas = &document["components"]["schemas"];
+  assert_eq!(schemas["OperationType"]["enum"], serde_json::to_value(operation_types())?);
+  assert_eq!(schemas["Operation"]["properties"]["type"]["$ref"], "#/components/schemas/OperationType");
+  assert_eq!(
+    schemas["AccountBalanceRequest"]["required"],
+    serde_json::json!(["network_identifier", "account_identifier"])
+  );
+  assert!(schemas["SearchTransactionsRequest"]["properties"]["cursor"].is_object());
+  let errors = &document["components"]["responses"]["Error"]["content"]["application/json"]["examples"];
+  for error in MinaMeshError::all_errors() {
+    assert_eq!(errors[error.error_code().to_string()]["value"], error.to_json());
+  }
+  Ok(())
+}
+
+#[tokio::test]
+async fn openapi_examples_match_fixtures() -> Result<()> {
+  let document = openapi_document(MinaMeshConfig::from_env().to_mina_mesh().await?).await?;
+  let groups = [
+    fixtures::account_balance(),
+    fixtures::account_balance_not_exists(),
+    fixtures::block(),
+    fixtures::block_not_found(),
+    fixtures::construction_combine(),
+    fixtures::construction_derive(),
+    fixtures::construction_hash(),
+    fixtures::construction_metadata(),
+    fixtures::construction_parse(),
+    fixtures::construction_payloads(),
+    fixtures::construction_preprocess(),
+    fixtures::mempool(),
+    fixtures::mempool_transaction(),
+    fixtures::network_list(),
+    fixtures::network_options(),
+    fixtures::network_status(),
+    fixtures::search_transactions(),
+  ];
+  // The example of an endpoint is the first request of its first group
+  let mut documented = HashSet::new();
+  for (path, requests) in groups {
+    if !documented.insert(path) {
+      continue;
+    }
+    let example = &document["paths"][path]["post"]["requestBody"]["content"]["application/json"]["example"];
+    assert_eq!(example, &serde_json::to_value(&requests[0])?, "example of {}", path);
+  }
+  Ok(())
+}
+
+#[tokio::test]
+async fn openapi_documents_served_endpoints() -> Result<()> {
+  let mut config = MinaMeshConfig::from_env();
+  config.offline = true;
+  config.archive_database_url = None;
+  config.network_id = Some("mina:devnet".to_string());
+  let document = openapi_document(config.to_mina_mesh().await?).await?;
+  let mut paths =
+    document["paths"].as_object().map(|paths| paths.keys().cloned().collect::<Vec<_>>()).unwrap_or_default();
+  paths.sort();
+  assert_eq!(
+    paths,
+    [
+      "/construction/combine",
+      "/construction/derive",
+      "/construction/hash",
+      "/construction/parse",
+      "/construction/payloads",
+      "/construction/preprocess",
+    ]
+  );
+  // Only the schemas of the served endpoints are documented
+  let schemas = &document["components"]["schemas"];
+  assert!(schemas["ConstructionPayloadsRequest"].is_object());
+  assert!(schemas["Operation"].is_object());
+  assert!(schemas["BlockResponse"].is_null());
+  Ok(())
+}
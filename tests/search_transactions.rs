@@ -1,9 +1,12 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use insta::assert_debug_snapshot;
 use mina_mesh::{
-  models::{AccountIdentifier, SearchTransactionsRequest, TransactionIdentifier},
+  models::{AccountIdentifier, CoinIdentifier, Currency, Operator, SearchTransactionsRequest, TransactionIdentifier},
   test::network_id,
-  MinaMeshConfig,
+  CommandKind, MinaMeshConfig, MinaMeshError, SearchTransactionsCursor, SearchTransactionsMetadata,
+  SearchTransactionsPageRequest, SortOrder,
 };
 
 #[tokio::test]
@@ -205,6 +208,42 @@ async fn search_transactions_offset_limit() -> Result<()> {
   Ok(())
 }
 
+#[tokio::test]
+async fn search_transactions_offset_pages() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let search_count_hits =
+    || mina_mesh.cache_stats().into_iter().find(|(name, _)| *name == "search_count").map_or(0, |(_, stats)| stats.hits);
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    max_block: Some(44),
+    limit: Some(7),
+    ..Default::default()
+  };
+
+  // Pages span user, internal and zkApp commands alike
+  let mut paged = Vec::new();
+  let mut pages = 0;
+  let hits = search_count_hits();
+  let total_count = loop {
+    pages += 1;
+    let offset = paged.len() as i64;
+    let page =
+      mina_mesh.search_transactions(SearchTransactionsRequest { offset: Some(offset), ..request.clone() }).await?;
+    paged.extend(page.transactions);
+    match page.next_offset {
+      Some(next_offset) => assert_eq!(next_offset, paged.len() as i64),
+      None => break page.total_count,
+    }
+  };
+  // The counts are only counted for the first page
+  assert_eq!(search_count_hits() - hits, pages - 1);
+
+  let all = mina_mesh.search_transactions(SearchTransactionsRequest { limit: Some(total_count), ..request }).await?;
+  assert_eq!(paged.len() as i64, total_count);
+  assert_eq!(paged, all.transactions);
+  Ok(())
+}
+
 #[tokio::test]
 async fn search_transactions_uc_include_timestamp() -> Result<()> {
   let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
@@ -271,3 +310,327 @@ async fn search_transactions_zk_include_timestamp() -> Result<()> {
   assert_debug_snapshot!(response);
   Ok(())
 }
+
+#[tokio::test]
+async fn search_transactions_read_replica() -> Result<()> {
+  let primary = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let mut config = MinaMeshConfig::from_env();
+  // The Archive Database stands in for its own replica, never lagging behind
+  config.archive_replica_database_url = config.archive_database_url.clone();
+  let with_replica = config.to_mina_mesh().await?;
+  assert!(with_replica.replica_pg_pool.is_some());
+
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    // cspell:disable-next-line
+    address: Some("B62qkd6yYALkQMq2SFd5B57bJbGBMA2QuGtLPMzRhhnvexRtVRycZWP".to_string()),
+    limit: Some(5),
+    ..Default::default()
+  };
+  assert_eq!(primary.search_transactions(request.clone()).await?, with_replica.search_transactions(request).await?);
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_statement_timeout() -> Result<()> {
+  let mut mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  mina_mesh.statement_timeouts.search_transactions = Some(Duration::from_nanos(1));
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    // cspell:disable-next-line
+    address: Some("B62qkd6yYALkQMq2SFd5B57bJbGBMA2QuGtLPMzRhhnvexRtVRycZWP".to_string()),
+    ..Default::default()
+  };
+  let response = mina_mesh.search_transactions(request).await;
+  assert!(matches!(response, Err(MinaMeshError::SqlTimeout(_))));
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_type() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    max_block: Some(400_181),
+    r#type: Some("delegate_change".to_string()),
+    limit: Some(10),
+    ..Default::default()
+  };
+  let response = mina_mesh.search_transactions(request).await?;
+
+  assert!(!response.transactions.is_empty());
+  for transaction in response.transactions {
+    assert!(transaction.transaction.operations.iter().any(|operation| operation.r#type == "delegate_change"));
+  }
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_operator_or() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    transaction_identifier: Some(Box::new(TransactionIdentifier::new(
+      // cspell:disable-next-line
+      "5JuotEHhjuYbu2oucyTiVhJX3Abx5DPL4NXnM7CP9hfJZLE5G8n9".to_string(),
+    ))),
+    // cspell:disable-next-line
+    address: Some("B62qkd6yYALkQMq2SFd5B57bJbGBMA2QuGtLPMzRhhnvexRtVRycZWP".to_string()),
+    limit: Some(5),
+    ..Default::default()
+  };
+  let by_hash = mina_mesh.search_transactions(SearchTransactionsRequest { address: None, ..request.clone() }).await?;
+  let by_address = mina_mesh
+    .search_transactions(SearchTransactionsRequest { transaction_identifier: None, ..request.clone() })
+    .await?;
+  let either =
+    mina_mesh.search_transactions(SearchTransactionsRequest { operator: Some(Operator::Or), ..request }).await?;
+
+  assert_eq!(by_hash.total_count, 1);
+  assert!(either.total_count >= by_address.total_count + by_hash.total_count - 1);
+  assert!(either.total_count <= by_address.total_count + by_hash.total_count);
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_unsupported_filters() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = SearchTransactionsRequest { network_identifier: Box::new(network_id()), ..Default::default() };
+  let requests = [
+    SearchTransactionsRequest { r#type: Some("transfer".to_string()), ..request.clone() },
+    SearchTransactionsRequest { currency: Some(Box::new(Currency::new("USD".to_string(), 2))), ..request.clone() },
+    SearchTransactionsRequest { coin_identifier: Some(Box::new(CoinIdentifier::new("coin".to_string()))), ..request },
+  ];
+  for request in requests {
+    let response = mina_mesh.search_transactions(request).await;
+    assert!(matches!(response, Err(MinaMeshError::Exception(_))));
+  }
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_legacy_token_id() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    transaction_identifier: Some(Box::new(TransactionIdentifier::new(
+      // cspell:disable-next-line
+      "5JvFoEyvuPu9zmi4bDGbhqsakre2SPQU1KKbeh2Lk5uC9eYrc2h2".to_string(),
+    ))),
+    ..Default::default()
+  };
+  let with_currency = |metadata| SearchTransactionsRequest {
+    currency: Some(Box::new(Currency { metadata, ..Currency::new("MINA".to_string(), 9) })),
+    ..request.clone()
+  };
+
+  // The legacy token ID of MINA finds the same commands as its symbol
+  let by_symbol = mina_mesh.search_transactions(with_currency(None)).await?;
+  let by_legacy_token_id =
+    mina_mesh.search_transactions(with_currency(Some(serde_json::json!({ "token_id": "1" })))).await?;
+  assert_eq!(by_symbol.total_count, 1);
+  assert_eq!(by_legacy_token_id, by_symbol);
+
+  // A token ID which is not a string is rejected
+  let response = mina_mesh
+    .search_transactions(SearchTransactionsRequest {
+      account_identifier: Some(Box::new(AccountIdentifier {
+        // cspell:disable-next-line
+        address: "B62qituGxc1ZNbWfz4SnftNUaJ78YYYsmuuuJr1FFHjbRqLir7tvBew".to_string(),
+        metadata: Some(serde_json::json!({ "token_id": 1 })),
+        ..Default::default()
+      })),
+      ..request
+    })
+    .await;
+  assert!(matches!(response, Err(MinaMeshError::JsonParse(_))));
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_block_and_timestamp_range() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    // cspell:disable-next-line
+    address: Some("B62qnFh3toTxk1gP1bvoo5v4L4GwbSwL4MBWV24R1xMntsKt1WRUbWd".to_string()),
+    max_block: Some(400_181),
+    include_timestamp: Some(true),
+    limit: Some(50),
+    ..Default::default()
+  };
+  let all = mina_mesh.search_transactions(request.clone()).await?;
+  let pivot = &all.transactions[all.transactions.len() / 2];
+  let page_request = |metadata| SearchTransactionsPageRequest {
+    request: request.clone(),
+    metadata: Some(metadata),
+    ..Default::default()
+  };
+
+  let min_block = pivot.block_identifier.index;
+  let by_height = mina_mesh
+    .search_transactions_page(page_request(SearchTransactionsMetadata {
+      min_block: Some(min_block),
+      ..Default::default()
+    }))
+    .await?;
+  assert!(by_height.response.total_count <= all.total_count);
+  assert!(by_height.response.transactions.iter().all(|transaction| transaction.block_identifier.index >= min_block));
+
+  let by_timestamp = mina_mesh
+    .search_transactions_page(page_request(SearchTransactionsMetadata {
+      min_timestamp: pivot.timestamp,
+      max_timestamp: pivot.timestamp,
+      ..Default::default()
+    }))
+    .await?;
+  assert!(!by_timestamp.response.transactions.is_empty());
+  assert!(by_timestamp.response.transactions.iter().all(|transaction| transaction.timestamp == pivot.timestamp));
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_cursor_walk() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    // cspell:disable-next-line
+    address: Some("B62qnFh3toTxk1gP1bvoo5v4L4GwbSwL4MBWV24R1xMntsKt1WRUbWd".to_string()),
+    max_block: Some(400_181),
+    limit: Some(7),
+    ..Default::default()
+  };
+
+  let mut cursor = String::new();
+  let mut walked = Vec::new();
+  let total_count = loop {
+    let page = mina_mesh
+      .search_transactions_page(SearchTransactionsPageRequest {
+        request: request.clone(),
+        cursor: Some(cursor.clone()),
+        ..Default::default()
+      })
+      .await?;
+    assert!(page.response.next_offset.is_none());
+    // An empty page means the walk is caught up
+    if page.response.transactions.is_empty() {
+      assert!(page.next_cursor.is_none());
+      break page.response.total_count;
+    }
+    walked.extend(page.response.transactions);
+    cursor = page.next_cursor.expect("a non-empty page has a cursor");
+  };
+
+  // The pages add up to a single page of all results, in the same order
+  let all = mina_mesh
+    .search_transactions_page(SearchTransactionsPageRequest {
+      request: SearchTransactionsRequest { limit: Some(total_count), ..request.clone() },
+      cursor: Some(String::new()),
+      ..Default::default()
+    })
+    .await?;
+  assert_eq!(walked.len() as i64, total_count);
+  assert_eq!(walked, all.response.transactions);
+  // The single page ends at the same position as the walk, which resumes from it
+  assert_eq!(all.next_cursor.as_ref(), Some(&cursor));
+  let resumed = mina_mesh
+    .search_transactions_page(SearchTransactionsPageRequest { request, cursor: all.next_cursor, ..Default::default() })
+    .await?;
+  assert!(resumed.response.transactions.is_empty());
+  assert!(walked.windows(2).all(|pair| pair[0].block_identifier.index <= pair[1].block_identifier.index));
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_sort_order() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request = SearchTransactionsRequest {
+    network_identifier: Box::new(network_id()),
+    // cspell:disable-next-line
+    address: Some("B62qnFh3toTxk1gP1bvoo5v4L4GwbSwL4MBWV24R1xMntsKt1WRUbWd".to_string()),
+    max_block: Some(400_181),
+    limit: Some(7),
+    ..Default::default()
+  };
+  let search_count_hits =
+    || mina_mesh.cache_stats().into_iter().find(|(name, _)| *name == "search_count").map_or(0, |(_, stats)| stats.hits);
+  let sorted = |order, request: SearchTransactionsRequest, cursor| SearchTransactionsPageRequest {
+    request,
+    cursor,
+    metadata: Some(SearchTransactionsMetadata { order: Some(order), ..Default::default() }),
+  };
+
+  // Pages by cursor, newest first, starting without one
+  let mut by_cursor = Vec::new();
+  let mut cursor = None;
+  let mut pages = 0;
+  let hits = search_count_hits();
+  let total_count = loop {
+    let page = mina_mesh.search_transactions_page(sorted(SortOrder::Desc, request.clone(), cursor)).await?;
+    assert!(page.response.next_offset.is_none());
+    pages += 1;
+    if page.response.transactions.is_empty() {
+      break page.response.total_count;
+    }
+    by_cursor.extend(page.response.transactions);
+    cursor = page.next_cursor;
+  };
+  // The total count is only counted for the first page
+  assert_eq!(search_count_hits() - hits, pages - 1);
+  assert_eq!(by_cursor.len() as i64, total_count);
+  assert!(by_cursor.windows(2).all(|pair| pair[0].block_identifier.index >= pair[1].block_identifier.index));
+
+  // A single page, oldest first
+  let ascending = mina_mesh
+    .search_transactions_page(sorted(
+      SortOrder::Asc,
+      SearchTransactionsRequest { limit: Some(total_count), ..request.clone() },
+      None,
+    ))
+    .await?;
+  assert!(ascending.next_cursor.is_some());
+  assert_eq!(ascending.response.transactions, by_cursor.into_iter().rev().collect::<Vec<_>>());
+
+  // Sorted results are not paged by offset
+  let response = mina_mesh
+    .search_transactions_page(sorted(SortOrder::Desc, SearchTransactionsRequest { offset: Some(7), ..request }, None))
+    .await;
+  assert!(matches!(response, Err(MinaMeshError::InvalidCursor(_))));
+  Ok(())
+}
+
+#[tokio::test]
+async fn search_transactions_cursor_rejects_offset() -> Result<()> {
+  let mina_mesh = MinaMeshConfig::from_env().to_mina_mesh().await?;
+  let request =
+    SearchTransactionsRequest { network_identifier: Box::new(network_id()), offset: Some(5), ..Default::default() };
+  let response = mina_mesh
+    .search_transactions_page(SearchTransactionsPageRequest {
+      request,
+      cursor: Some(String::new()),
+      ..Default::default()
+    })
+    .await;
+  assert!(matches!(response, Err(MinaMeshError::InvalidCursor(_))));
+  Ok(())
+}
+
+#[test]
+fn search_transactions_cursor_encoding() {
+  let cursor = SearchTransactionsCursor {
+    height: 400_175,
+    block_id: 401_000,
+    kind: CommandKind::ZkApp,
+    sequence_no: 3,
+    secondary_sequence_no: 0,
+    id: 12_345,
+  };
+  assert_eq!(SearchTransactionsCursor::decode(&cursor.encode()), Ok(cursor));
+  assert!(matches!(SearchTransactionsCursor::decode("not a cursor"), Err(MinaMeshError::InvalidCursor(_))));
+  // All zkApp commands of the block of a user command follow it, no internal
+  // command does
+  let cursor = SearchTransactionsCursor { kind: CommandKind::User, ..cursor };
+  assert_eq!(cursor.bound(CommandKind::User), cursor);
+  assert_eq!(cursor.bound(CommandKind::ZkApp).sequence_no, -1);
+  assert_eq!(cursor.bound(CommandKind::Internal).sequence_no, i32::MAX);
+}
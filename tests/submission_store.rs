@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use mina_mesh::{CacheConfig, FileSubmissionStore, MemorySubmissionStore, Reservation, SubmissionStore};
+
+fn store_path(name: &str) -> PathBuf {
+  let path = std::env::temp_dir().join(format!("mina-mesh-{}-{}", name, std::process::id()));
+  let _ = std::fs::remove_file(&path);
+  let _ = std::fs::remove_dir_all(format!("{}.pending", path.display()));
+  path
+}
+
+#[test]
+fn memory_submission_store() -> Result<()> {
+  let store = MemorySubmissionStore::new(CacheConfig { capacity: 1, ttl: None });
+  assert_eq!(store.get("5Ju1")?, None);
+  store.insert("5Ju1", "5Ju1")?;
+  assert_eq!(store.get("5Ju1")?, Some("5Ju1".to_string()));
+  store.insert("5Ju2", "5Ju2")?;
+  assert_eq!(store.get("5Ju1")?, None);
+  Ok(())
+}
+
+#[test]
+fn file_submission_store_survives_restart() -> Result<()> {
+  let path = store_path("restart");
+  let store = FileSubmissionStore::open(&path)?;
+  store.insert("5Ju1", "5Ju1")?;
+  drop(store);
+
+  let store = FileSubmissionStore::open(&path)?;
+  assert_eq!(store.get("5Ju1")?, Some("5Ju1".to_string()));
+  assert_eq!(store.get("5Ju2")?, None);
+  assert_eq!(store.stats().entries, 1);
+  std::fs::remove_file(path)?;
+  Ok(())
+}
+
+#[test]
+fn file_submission_store_shared() -> Result<()> {
+  let path = store_path("shared");
+  let first = FileSubmissionStore::open(&path)?;
+  let second = FileSubmissionStore::open(&path)?;
+  assert_eq!(second.get("5Ju1")?, None);
+  first.insert("5Ju1", "5Ju1")?;
+  assert_eq!(second.get("5Ju1")?, Some("5Ju1".to_string()));
+  // Its own entries are read back without being duplicated
+  assert_eq!(first.get("5Ju2")?, None);
+  assert_eq!(first.stats().entries, 1);
+  std::fs::remove_file(path)?;
+  Ok(())
+}
+
+#[test]
+fn submission_store_reserves_hash_until_released() -> Result<()> {
+  let path = store_path("reserve");
+  let stores: [Box<dyn SubmissionStore>; 2] = [
+    Box::new(MemorySubmissionStore::new(CacheConfig { capacity: 1, ttl: None })),
+    Box::new(FileSubmissionStore::open(&path)?),
+  ];
+  for store in stores {
+    assert_eq!(store.reserve("5Ju1")?, Reservation::Reserved);
+    assert_eq!(store.reserve("5Ju1")?, Reservation::Pending);
+    store.release("5Ju1");
+    assert_eq!(store.reserve("5Ju1")?, Reservation::Reserved);
+    store.insert("5Ju1", "5Ju1")?;
+    assert_eq!(store.reserve("5Ju1")?, Reservation::Submitted("5Ju1".to_string()));
+  }
+  std::fs::remove_file(path)?;
+  Ok(())
+}
+
+#[test]
+fn file_submission_store_shares_reservations() -> Result<()> {
+  let path = store_path("shared-reservations");
+  let first = FileSubmissionStore::open(&path)?;
+  let second = FileSubmissionStore::open(&path)?;
+  assert_eq!(first.reserve("5Ju1")?, Reservation::Reserved);
+  assert_eq!(second.reserve("5Ju1")?, Reservation::Pending);
+  first.release("5Ju1");
+  assert_eq!(second.reserve("5Ju1")?, Reservation::Reserved);
+  assert_eq!(first.reserve("5Ju1")?, Reservation::Pending);
+  second.insert("5Ju1", "5Ju1")?;
+  assert_eq!(first.reserve("5Ju1")?, Reservation::Submitted("5Ju1".to_string()));
+  std::fs::remove_file(&path)?;
+  std::fs::remove_dir_all(format!("{}.pending", path.display()))?;
+  Ok(())
+}